@@ -0,0 +1,118 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::language_pack::get_language_pack;
+use crate::verify::StackPlan;
+
+/// A command that checks a generated service is consistent, run in the
+/// service directory, e.g. `cargo check --offline`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CheckCommand {
+    pub program: String,
+    pub args: Vec<String>,
+}
+
+impl CheckCommand {
+    pub fn new(program: &str, args: &[&str]) -> Self {
+        Self {
+            program: program.to_string(),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+        }
+    }
+}
+
+impl fmt::Display for CheckCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.program)?;
+        for arg in &self.args {
+            write!(f, " {arg}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Outcome of one service's check commands.
+#[derive(Debug, Clone, Serialize)]
+pub struct BuildCheck {
+    pub service: String,
+    /// Commands that ran, in order; a failing command ends the list.
+    pub commands: Vec<String>,
+    pub success: bool,
+    /// Combined stdout and stderr of the commands.
+    pub output: String,
+}
+
+impl fmt::Display for BuildCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let status = if self.success { "ok" } else { "failed" };
+        write!(f, "services/{}: {status}", self.service)?;
+        if let Some(command) = self.commands.last().filter(|_| !self.success) {
+            write!(f, " (`{command}`)")?;
+        }
+        Ok(())
+    }
+}
+
+/// Run every service's check commands against a copy of the generated
+/// `files`, so build artifacts never end up in the scaffold.
+pub fn run_build_checks(
+    plan: &StackPlan,
+    out_dir: &Path,
+    files: &[PathBuf],
+) -> Result<Vec<BuildCheck>> {
+    let work_dir = tempfile::tempdir().context("Failed to create build check directory")?;
+    for file in files {
+        let target = work_dir.path().join(file);
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::copy(out_dir.join(file), &target)
+            .with_context(|| format!("Failed to copy {file:?} for the build check"))?;
+    }
+
+    let mut checks = Vec::new();
+    for service in &plan.services {
+        let pack = get_language_pack(&service.language)?;
+        let service_dir = work_dir.path().join("services").join(&service.name);
+
+        let mut check = BuildCheck {
+            service: service.name.clone(),
+            commands: Vec::new(),
+            success: true,
+            output: String::new(),
+        };
+        for command in pack.check_commands(service) {
+            check.commands.push(command.to_string());
+            let output = Command::new(&command.program)
+                .args(&command.args)
+                .current_dir(&service_dir)
+                .output();
+            match output {
+                Ok(output) => {
+                    check
+                        .output
+                        .push_str(&String::from_utf8_lossy(&output.stdout));
+                    check
+                        .output
+                        .push_str(&String::from_utf8_lossy(&output.stderr));
+                    check.success = output.status.success();
+                }
+                Err(err) => {
+                    check
+                        .output
+                        .push_str(&format!("Failed to run `{command}`: {err}\n"));
+                    check.success = false;
+                }
+            }
+            if !check.success {
+                break;
+            }
+        }
+        checks.push(check);
+    }
+
+    Ok(checks)
+}
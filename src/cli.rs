@@ -1,4 +1,4 @@
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use std::path::PathBuf;
 
 #[derive(Parser)]
@@ -8,45 +8,136 @@ use std::path::PathBuf;
 pub struct Cli {
     #[command(subcommand)]
     pub command: Commands,
+
+    /// Directory of language pack plugins (executables)
+    #[arg(long, global = true, value_name = "DIR")]
+    pub plugins: Option<PathBuf>,
 }
 
 #[derive(Subcommand)]
 pub enum Commands {
     /// Apply a plan to generate scaffold
-    Apply {
+    Apply(ApplyArgs),
+
+    /// Verify a plan without generating
+    Verify {
         /// Path to plan.json
         #[arg(short, long, value_name = "FILE")]
         plan: PathBuf,
 
-        /// Random seed for deterministic generation
-        #[arg(long)]
-        seed: Option<u64>,
-
-        /// Repository to push to (e.g., github:owner/repo)
-        #[arg(long)]
-        repo: Option<String>,
-
         /// Path to policy file
         #[arg(long)]
         policy: Option<PathBuf>,
 
-        /// Output directory
+        /// Output format for the result
+        #[arg(long, value_enum, default_value_t)]
+        format: OutputFormat,
+    },
+
+    /// Report generated files that changed since the scaffold was generated
+    Check {
+        /// Scaffold directory containing weave.manifest.json
         #[arg(long, default_value = "./scaffold")]
-        out: PathBuf,
+        dir: PathBuf,
 
-        /// Verify only, don't generate
-        #[arg(long)]
-        verify: bool,
+        /// Output format for the result
+        #[arg(long, value_enum, default_value_t)]
+        format: OutputFormat,
     },
 
-    /// Verify a plan without generating
-    Verify {
-        /// Path to plan.json
-        #[arg(short, long, value_name = "FILE")]
-        plan: PathBuf,
+    /// Regenerate a scaffold, three-way merging into locally edited files
+    Upgrade(UpgradeArgs),
 
-        /// Path to policy file
-        #[arg(long)]
-        policy: Option<PathBuf>,
+    /// List the supported language/framework/runtime combinations
+    Packs {
+        /// Output format for the result
+        #[arg(long, value_enum, default_value_t)]
+        format: OutputFormat,
     },
+
+    /// Print the JSON Schema for plan.json
+    Schema,
+}
+
+#[derive(Args)]
+pub struct ApplyArgs {
+    /// Path to plan.json
+    #[arg(short, long, value_name = "FILE")]
+    pub plan: PathBuf,
+
+    /// Random seed for deterministic generation
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    /// Repository to push to (github:, gitlab:, gitea: or a git URL)
+    #[arg(long)]
+    pub repo: Option<String>,
+
+    /// Branch to push the scaffold to [default: runeweave/<project>-<seed>]
+    #[arg(long, requires = "repo")]
+    pub branch: Option<String>,
+
+    /// Branch the pull request targets
+    #[arg(long, default_value = "main", requires = "repo")]
+    pub base: String,
+
+    /// Commit to --repo even if the output's repository has uncommitted changes
+    #[arg(long, requires = "repo")]
+    pub force: bool,
+
+    /// Path to policy file
+    #[arg(long)]
+    pub policy: Option<PathBuf>,
+
+    /// Output directory
+    #[arg(long, default_value = "./scaffold")]
+    pub out: PathBuf,
+
+    /// Directory of templates overriding the built-in ones by name
+    #[arg(long, value_name = "DIR")]
+    pub templates: Option<PathBuf>,
+
+    /// Verify only, don't generate
+    #[arg(long)]
+    pub verify: bool,
+
+    /// Check each generated service with its toolchain (e.g. `cargo check`)
+    #[arg(long, conflicts_with = "verify")]
+    pub build_check: bool,
+
+    /// Output format for the result
+    #[arg(long, value_enum, default_value_t)]
+    pub format: OutputFormat,
+}
+
+#[derive(Args)]
+pub struct UpgradeArgs {
+    /// Scaffold directory containing weave.manifest.json
+    #[arg(long, default_value = "./scaffold")]
+    pub dir: PathBuf,
+
+    /// New plan [default: the plan recorded in the manifest]
+    #[arg(short, long, value_name = "FILE")]
+    pub plan: Option<PathBuf>,
+
+    /// Path to policy file
+    #[arg(long)]
+    pub policy: Option<PathBuf>,
+
+    /// Directory of templates overriding the built-in ones by name
+    #[arg(long, value_name = "DIR")]
+    pub templates: Option<PathBuf>,
+
+    /// Output format for the result
+    #[arg(long, value_enum, default_value_t)]
+    pub format: OutputFormat,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Log lines only
+    #[default]
+    Text,
+    /// A JSON report on stdout
+    Json,
 }
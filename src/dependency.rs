@@ -0,0 +1,609 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::fmt;
+
+use crate::verify::Language;
+
+/// Package registry a dependency is resolved from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Ecosystem {
+    Crates,
+    Npm,
+    PyPI,
+    Go,
+    Maven,
+    NuGet,
+}
+
+impl Ecosystem {
+    /// Registry of a built-in language; plugins declare their own.
+    pub fn for_language(language: &Language) -> Option<Self> {
+        match language {
+            Language::Rust => Some(Ecosystem::Crates),
+            Language::Node | Language::Deno => Some(Ecosystem::Npm),
+            Language::Python => Some(Ecosystem::PyPI),
+            Language::Go => Some(Ecosystem::Go),
+            Language::Java => Some(Ecosystem::Maven),
+            Language::DotNet => Some(Ecosystem::NuGet),
+            Language::Plugin(_) => None,
+        }
+    }
+
+    /// Version requirement used when a dependency does not specify one.
+    fn any_version(self) -> Option<&'static str> {
+        match self {
+            Ecosystem::Crates | Ecosystem::Npm | Ecosystem::PyPI | Ecosystem::NuGet => Some("*"),
+            Ecosystem::Go | Ecosystem::Maven => None,
+        }
+    }
+
+    /// Canonical form of a package name, used when comparing names.
+    pub(crate) fn normalize_name(self, name: &str) -> String {
+        match self {
+            Ecosystem::Crates => name.to_lowercase().replace('_', "-"),
+            Ecosystem::PyPI => {
+                let lower = name.to_lowercase();
+                let mut normalized = String::with_capacity(lower.len());
+                for c in lower.chars() {
+                    if matches!(c, '-' | '_' | '.') {
+                        if !normalized.ends_with('-') {
+                            normalized.push('-');
+                        }
+                    } else {
+                        normalized.push(c);
+                    }
+                }
+                normalized
+            }
+            Ecosystem::NuGet => name.to_lowercase(),
+            Ecosystem::Npm | Ecosystem::Go | Ecosystem::Maven => name.to_string(),
+        }
+    }
+
+    fn dialect(self) -> Dialect {
+        match self {
+            Ecosystem::Crates => Dialect::Cargo,
+            Ecosystem::PyPI => Dialect::Pep440,
+            _ => Dialect::Npm,
+        }
+    }
+}
+
+impl fmt::Display for Ecosystem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Ecosystem::Crates => "crate",
+            Ecosystem::Npm => "npm package",
+            Ecosystem::PyPI => "PyPI package",
+            Ecosystem::Go => "Go module",
+            Ecosystem::Maven => "Maven artifact",
+            Ecosystem::NuGet => "NuGet package",
+        };
+        f.write_str(name)
+    }
+}
+
+/// A dependency written into a generated service manifest.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Dependency {
+    pub ecosystem: Ecosystem,
+    pub name: String,
+    /// Version requirement in the ecosystem's own syntax.
+    pub version: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub features: Vec<String>,
+    /// Development/test-only dependency.
+    pub dev: bool,
+}
+
+impl Dependency {
+    pub fn new(ecosystem: Ecosystem, name: &str, version: &str) -> Self {
+        Self {
+            ecosystem,
+            name: name.to_string(),
+            version: version.to_string(),
+            features: Vec::new(),
+            dev: false,
+        }
+    }
+
+    pub fn with_features(mut self, features: &[&str]) -> Self {
+        self.features = features.iter().map(|f| f.to_string()).collect();
+        self
+    }
+
+    pub fn dev(mut self) -> Self {
+        self.dev = true;
+        self
+    }
+
+    /// Parse a dependency declared in `Service.dependencies`.
+    ///
+    /// Accepts `name`, `name@requirement` and, for PyPI, PEP 508 style
+    /// `name>=1.0` specifiers.
+    pub fn parse(ecosystem: Ecosystem, spec: &str) -> Result<Self> {
+        let (name, requirement) = split_spec(ecosystem, spec);
+        if name.is_empty() {
+            anyhow::bail!("Invalid {ecosystem} dependency '{spec}': missing name");
+        }
+
+        let version = match requirement {
+            Some(requirement) => {
+                if matches!(ecosystem, Ecosystem::Crates | Ecosystem::Npm | Ecosystem::PyPI)
+                    && VersionRange::parse(ecosystem, requirement).is_none()
+                {
+                    anyhow::bail!(
+                        "Invalid {ecosystem} dependency '{spec}': cannot parse version requirement '{requirement}'"
+                    );
+                }
+                requirement.to_string()
+            }
+            None => match ecosystem.any_version() {
+                Some(any) => any.to_string(),
+                None => anyhow::bail!(
+                    "Invalid {ecosystem} dependency '{spec}': a version is required (use {name}@<version>)"
+                ),
+            },
+        };
+
+        Ok(Self::new(ecosystem, name, &version))
+    }
+
+    /// Whether `other` refers to the same package as `self`.
+    pub fn same_package(&self, other: &Dependency) -> bool {
+        self.ecosystem == other.ecosystem
+            && self.ecosystem.normalize_name(&self.name)
+                == other.ecosystem.normalize_name(&other.name)
+    }
+}
+
+impl fmt::Display for Dependency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}@{}", self.name, self.version)
+    }
+}
+
+/// A `deny` entry from the policy, e.g. `left-pad@*` or `cryptography<42.0`.
+#[derive(Debug, Clone)]
+pub struct DenyRule {
+    pub ecosystem: Ecosystem,
+    pub spec: String,
+    name: String,
+    range: VersionRange,
+}
+
+impl DenyRule {
+    pub fn parse(ecosystem: Ecosystem, spec: &str) -> Result<Self> {
+        let (name, requirement) = split_spec(ecosystem, spec);
+        if name.is_empty() {
+            anyhow::bail!("Invalid {ecosystem} deny rule '{spec}': missing name");
+        }
+
+        let range = match requirement {
+            Some(requirement) => VersionRange::parse(ecosystem, requirement).ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Invalid {ecosystem} deny rule '{spec}': cannot parse version range '{requirement}'"
+                )
+            })?,
+            None => VersionRange::any(),
+        };
+
+        Ok(Self {
+            ecosystem,
+            spec: spec.to_string(),
+            name: ecosystem.normalize_name(name),
+            range,
+        })
+    }
+
+    /// Whether any version admitted by `dependency` falls in the denied range.
+    pub fn matches(&self, dependency: &Dependency) -> bool {
+        if dependency.ecosystem != self.ecosystem
+            || self.ecosystem.normalize_name(&dependency.name) != self.name
+        {
+            return false;
+        }
+
+        // Requirements we cannot interpret are treated as "any version"
+        let requested = VersionRange::parse(self.ecosystem, &dependency.version)
+            .unwrap_or_else(VersionRange::any);
+        requested.intersects(&self.range)
+    }
+}
+
+/// Split `name@requirement` (or a PEP 508 specifier) into its parts.
+fn split_spec(ecosystem: Ecosystem, spec: &str) -> (&str, Option<&str>) {
+    let spec = spec.trim();
+
+    // Scoped npm packages start with '@', so only look for a separator after it
+    let at = spec
+        .char_indices()
+        .skip(1)
+        .filter(|(_, c)| *c == '@')
+        .map(|(i, _)| i)
+        .last();
+    if let Some(at) = at {
+        let requirement = spec[at + 1..].trim();
+        return (
+            spec[..at].trim(),
+            (!requirement.is_empty()).then_some(requirement),
+        );
+    }
+
+    if ecosystem == Ecosystem::PyPI {
+        if let Some(op) = spec.find(['<', '>', '=', '!', '~', '^']) {
+            let requirement = spec[op..].trim();
+            return (
+                spec[..op].trim(),
+                (!requirement.is_empty()).then_some(requirement),
+            );
+        }
+    }
+
+    (spec, None)
+}
+
+/// How bare versions and comparator lists are interpreted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Dialect {
+    /// Cargo: bare versions are caret requirements, comparators joined by `,`.
+    Cargo,
+    /// npm: bare versions are exact (or x-ranges), comparators joined by spaces.
+    Npm,
+    /// PEP 440 (plus Poetry's `^`/`~`): bare versions are `==`, joined by `,`.
+    Pep440,
+}
+
+/// Release version compared segment-wise, with missing segments as zero.
+/// Pre-release and build metadata are ignored.
+#[derive(Debug, Clone)]
+struct Version(Vec<u64>);
+
+impl Version {
+    fn bump(parts: &[u64], index: usize) -> Self {
+        let mut bumped = parts[..=index].to_vec();
+        bumped[index] += 1;
+        Version(bumped)
+    }
+}
+
+impl PartialEq for Version {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Version {}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let len = self.0.len().max(other.0.len());
+        (0..len)
+            .map(|i| {
+                let a = self.0.get(i).copied().unwrap_or(0);
+                let b = other.0.get(i).copied().unwrap_or(0);
+                a.cmp(&b)
+            })
+            .find(|o| o.is_ne())
+            .unwrap_or(Ordering::Equal)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Bound {
+    version: Version,
+    inclusive: bool,
+}
+
+/// A contiguous range of versions; `None` bounds are unbounded.
+#[derive(Debug, Clone)]
+struct Interval {
+    lower: Option<Bound>,
+    upper: Option<Bound>,
+}
+
+impl Interval {
+    fn all() -> Self {
+        Self {
+            lower: None,
+            upper: None,
+        }
+    }
+
+    fn between(lower: Version, upper: Version) -> Self {
+        Self {
+            lower: Some(Bound {
+                version: lower,
+                inclusive: true,
+            }),
+            upper: Some(Bound {
+                version: upper,
+                inclusive: false,
+            }),
+        }
+    }
+
+    fn exactly(version: Version) -> Self {
+        Self {
+            lower: Some(Bound {
+                version: version.clone(),
+                inclusive: true,
+            }),
+            upper: Some(Bound {
+                version,
+                inclusive: true,
+            }),
+        }
+    }
+
+    fn at_least(version: Version, inclusive: bool) -> Self {
+        Self {
+            lower: Some(Bound { version, inclusive }),
+            upper: None,
+        }
+    }
+
+    fn below(version: Version, inclusive: bool) -> Self {
+        Self {
+            lower: None,
+            upper: Some(Bound { version, inclusive }),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        match (&self.lower, &self.upper) {
+            (Some(lower), Some(upper)) => match lower.version.cmp(&upper.version) {
+                Ordering::Greater => true,
+                Ordering::Equal => !(lower.inclusive && upper.inclusive),
+                Ordering::Less => false,
+            },
+            _ => false,
+        }
+    }
+
+    fn intersect(&self, other: &Interval) -> Interval {
+        let lower = match (&self.lower, &other.lower) {
+            (Some(a), Some(b)) => Some(match a.version.cmp(&b.version) {
+                Ordering::Greater => a.clone(),
+                Ordering::Less => b.clone(),
+                Ordering::Equal => Bound {
+                    version: a.version.clone(),
+                    inclusive: a.inclusive && b.inclusive,
+                },
+            }),
+            (a, b) => a.clone().or_else(|| b.clone()),
+        };
+        let upper = match (&self.upper, &other.upper) {
+            (Some(a), Some(b)) => Some(match a.version.cmp(&b.version) {
+                Ordering::Less => a.clone(),
+                Ordering::Greater => b.clone(),
+                Ordering::Equal => Bound {
+                    version: a.version.clone(),
+                    inclusive: a.inclusive && b.inclusive,
+                },
+            }),
+            (a, b) => a.clone().or_else(|| b.clone()),
+        };
+        Interval { lower, upper }
+    }
+}
+
+/// A set of versions described by a requirement, as a union of intervals.
+#[derive(Debug, Clone)]
+struct VersionRange(Vec<Interval>);
+
+impl VersionRange {
+    fn any() -> Self {
+        VersionRange(vec![Interval::all()])
+    }
+
+    fn intersect(&self, other: &VersionRange) -> VersionRange {
+        VersionRange(
+            self.0
+                .iter()
+                .flat_map(|a| other.0.iter().map(move |b| a.intersect(b)))
+                .filter(|i| !i.is_empty())
+                .collect(),
+        )
+    }
+
+    fn intersects(&self, other: &VersionRange) -> bool {
+        !self.intersect(other).0.is_empty()
+    }
+
+    fn parse(ecosystem: Ecosystem, requirement: &str) -> Option<Self> {
+        let dialect = ecosystem.dialect();
+        let mut union = Vec::new();
+
+        for alternative in requirement.split("||") {
+            let mut range = VersionRange::any();
+            for comparator in comparators(dialect, alternative.trim())? {
+                range = range.intersect(&parse_comparator(dialect, &comparator)?);
+            }
+            union.extend(range.0);
+        }
+
+        Some(VersionRange(union))
+    }
+}
+
+/// Split one `||` alternative into individual comparators.
+fn comparators(dialect: Dialect, requirement: &str) -> Option<Vec<String>> {
+    if dialect != Dialect::Npm {
+        return Some(
+            requirement
+                .split(',')
+                .map(|c| c.trim().to_string())
+                .filter(|c| !c.is_empty())
+                .collect(),
+        );
+    }
+
+    // npm hyphen range: "1.2.3 - 2.3.4"
+    if let Some((from, to)) = requirement.split_once(" - ") {
+        return Some(vec![format!(">={}", from.trim()), hyphen_upper(to.trim())?]);
+    }
+
+    // npm allows whitespace between an operator and its version
+    let mut result: Vec<String> = Vec::new();
+    for token in requirement.split_whitespace() {
+        match result.last_mut() {
+            Some(last) if last.chars().all(|c| "<>=~^".contains(c)) => last.push_str(token),
+            _ => result.push(token.to_string()),
+        }
+    }
+    Some(result)
+}
+
+fn hyphen_upper(version: &str) -> Option<String> {
+    let (parts, _) = parse_partial(version)?;
+    Some(if parts.len() >= 3 {
+        format!("<={version}")
+    } else if parts.is_empty() {
+        "*".to_string()
+    } else {
+        let bumped = Version::bump(&parts, parts.len() - 1);
+        format!(
+            "<{}",
+            bumped
+                .0
+                .iter()
+                .map(u64::to_string)
+                .collect::<Vec<_>>()
+                .join(".")
+        )
+    })
+}
+
+/// Parse a possibly partial version (`1`, `1.2`, `1.2.x`, `v1.2.3-beta`).
+/// Returns the numeric segments before any wildcard and whether a wildcard
+/// segment was present.
+fn parse_partial(version: &str) -> Option<(Vec<u64>, bool)> {
+    let version = version.trim().trim_start_matches(['v', 'V']);
+    let release = version.split(['-', '+']).next().unwrap_or_default();
+    if release.is_empty() {
+        return None;
+    }
+
+    let mut parts = Vec::new();
+    for segment in release.split('.') {
+        if matches!(segment, "*" | "x" | "X") {
+            return Some((parts, true));
+        }
+        parts.push(segment.parse().ok()?);
+    }
+    Some((parts, false))
+}
+
+fn parse_comparator(dialect: Dialect, comparator: &str) -> Option<VersionRange> {
+    const OPERATORS: [&str; 11] = ["===", "~=", "==", "!=", ">=", "<=", "^", "~", "=", ">", "<"];
+
+    let comparator = comparator.trim();
+    if comparator.is_empty() || matches!(comparator, "*" | "x" | "X") {
+        return Some(VersionRange::any());
+    }
+
+    let op = OPERATORS
+        .iter()
+        .find(|op| comparator.starts_with(**op))
+        .copied()
+        .unwrap_or("");
+    let (parts, wildcard) = parse_partial(&comparator[op.len()..])?;
+    let n = parts.len();
+    let version = Version(parts.clone());
+    let partial = n < 3 || wildcard;
+
+    // Range matching every version that starts with `parts`
+    let prefix = || {
+        if n == 0 {
+            Interval::all()
+        } else {
+            Interval::between(version.clone(), Version::bump(&parts, n - 1))
+        }
+    };
+
+    let op = match (op, dialect) {
+        ("", Dialect::Cargo) => "^",
+        ("", Dialect::Npm) => "=",
+        ("", Dialect::Pep440) => "==",
+        (op, _) => op,
+    };
+
+    let interval = match op {
+        "^" => {
+            if n == 0 {
+                Interval::all()
+            } else {
+                let index = parts.iter().position(|p| *p != 0).unwrap_or(n - 1);
+                Interval::between(version.clone(), Version::bump(&parts, index.min(n - 1)))
+            }
+        }
+        "~" => match n {
+            0 => Interval::all(),
+            1 => Interval::between(version.clone(), Version::bump(&parts, 0)),
+            _ => Interval::between(version.clone(), Version::bump(&parts, 1)),
+        },
+        "~=" => {
+            if n < 2 {
+                return None;
+            }
+            Interval::between(version.clone(), Version::bump(&parts, n - 2))
+        }
+        "=" => {
+            if partial {
+                prefix()
+            } else {
+                Interval::exactly(version.clone())
+            }
+        }
+        "==" | "===" => {
+            if wildcard {
+                prefix()
+            } else {
+                Interval::exactly(version.clone())
+            }
+        }
+        "!=" => {
+            let excluded = if wildcard {
+                prefix()
+            } else {
+                Interval::exactly(version.clone())
+            };
+            let mut outside = Vec::new();
+            if let Some(lower) = excluded.lower {
+                outside.push(Interval::below(lower.version, !lower.inclusive));
+            }
+            if let Some(upper) = excluded.upper {
+                outside.push(Interval::at_least(upper.version, !upper.inclusive));
+            }
+            return Some(VersionRange(outside));
+        }
+        ">=" => Interval::at_least(version.clone(), true),
+        ">" => {
+            if partial && dialect != Dialect::Pep440 && n > 0 {
+                Interval::at_least(Version::bump(&parts, n - 1), true)
+            } else {
+                Interval::at_least(version.clone(), false)
+            }
+        }
+        "<" => Interval::below(version.clone(), false),
+        "<=" => {
+            if partial && dialect != Dialect::Pep440 && n > 0 {
+                Interval::below(Version::bump(&parts, n - 1), false)
+            } else {
+                Interval::below(version.clone(), true)
+            }
+        }
+        _ => return None,
+    };
+
+    Some(VersionRange(vec![interval]))
+}
@@ -0,0 +1,121 @@
+use std::path::PathBuf;
+use thiserror::Error;
+
+use crate::build_check::BuildCheck;
+use crate::manifest::Drift;
+use crate::upgrade::Upgrade;
+use crate::verify::{PolicyViolation, SchemaViolation};
+
+/// Errors surfaced by RuneWeave, grouped by the process exit code SPEC.md
+/// assigns to them.
+#[derive(Debug, Error)]
+pub enum WeaveError {
+    /// plan.json could not be read or is not valid JSON.
+    #[error("Failed to load plan {}: {reason:#}", path.display())]
+    Plan {
+        path: PathBuf,
+        reason: anyhow::Error,
+    },
+
+    /// plan.json does not match the stack schema.
+    #[error(
+        "Plan does not match stack schema ({} violation(s)):\n{}",
+        violations.len(),
+        list(violations.iter().map(|v| format!("{}:{v}", path.display())))
+    )]
+    Schema {
+        path: PathBuf,
+        violations: Vec<SchemaViolation>,
+    },
+
+    /// The policy file could not be loaded or contains invalid rules.
+    #[error("Invalid policy: {0:#}")]
+    InvalidPolicy(anyhow::Error),
+
+    /// The plan breaks one or more policy rules.
+    #[error(
+        "Policy check failed ({} violation(s)):\n{}",
+        .0.len(),
+        list(.0.iter().map(ToString::to_string))
+    )]
+    Policy(Vec<PolicyViolation>),
+
+    /// Templates could not be rendered or written.
+    #[error("Failed to render scaffold: {0:#}")]
+    Render(anyhow::Error),
+
+    /// Generated files no longer match `weave.manifest.json`.
+    #[error(
+        "Scaffold has drifted from its manifest ({} change(s)):\n{}",
+        .0.len(),
+        list(
+            .0.modified.iter().map(|f| format!("modified: {f}"))
+                .chain(.0.deleted.iter().map(|f| format!("deleted: {f}")))
+                .chain(.0.added.iter().map(|f| format!("added: {f}")))
+        )
+    )]
+    Drift(Drift),
+
+    /// `upgrade` left files that were changed on both sides to resolve.
+    #[error(
+        "Upgrade left {} conflict(s) to resolve:\n{}",
+        .0.conflicts.len(),
+        list(.0.conflicts.iter().map(|f| format!("conflict: {f}")))
+    )]
+    Conflict(Upgrade),
+
+    /// `--build-check` commands failed for one or more services.
+    #[error(
+        "Build check failed for {} service(s):\n{}",
+        .0.iter().filter(|c| !c.success).count(),
+        list(.0.iter().filter(|c| !c.success).map(|c| format!("{c}\n{}", indent(&c.output))))
+    )]
+    BuildCheck(Vec<BuildCheck>),
+
+    /// Preparing, pushing or opening a pull request for the repository failed.
+    #[error("Git operation failed: {0:#}")]
+    Git(anyhow::Error),
+
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+impl WeaveError {
+    /// Exit code documented in SPEC.md: 1 for plan/schema problems (and any
+    /// other failure), 2 for policy failures, 3 for git push/PR failures.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            WeaveError::InvalidPolicy(_) | WeaveError::Policy(_) => 2,
+            WeaveError::Git(_) => 3,
+            WeaveError::Plan { .. }
+            | WeaveError::Schema { .. }
+            | WeaveError::Render(_)
+            | WeaveError::Drift(_)
+            | WeaveError::Conflict(_)
+            | WeaveError::BuildCheck(_)
+            | WeaveError::Io(_)
+            | WeaveError::Other(_) => 1,
+        }
+    }
+}
+
+fn list(items: impl Iterator<Item = String>) -> String {
+    items
+        .map(|item| format!("  {item}"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Last lines of a command's output, indented below its list item.
+fn indent(output: &str) -> String {
+    const TAIL: usize = 20;
+    let lines: Vec<_> = output.lines().collect();
+    lines[lines.len().saturating_sub(TAIL)..]
+        .iter()
+        .map(|line| format!("      {line}"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
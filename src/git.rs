@@ -1,77 +1,823 @@
-use anyhow::Result;
-use std::path::Path;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tera::{Context as TeraContext, Tera};
+
+use crate::error::WeaveError;
+use crate::manifest::WeaveManifest;
 
 #[cfg(not(target_arch = "wasm32"))]
-use anyhow::Context;
+use git2::{
+    Cred, CredentialType, ObjectType, Oid, PushOptions, RemoteCallbacks, Repository, Signature,
+    StatusOptions, Tree,
+};
 #[cfg(not(target_arch = "wasm32"))]
-use git2::{Repository, Signature};
+use std::collections::BTreeMap;
 
 pub struct GitOps {
-    #[allow(dead_code)]
-    pub repo_url: String,
+    /// URL the scaffold branch is pushed to.
+    pub remote_url: String,
+    /// Opens the pull request once the branch is pushed.
+    pub forge: Option<Box<dyn ForgeClient>>,
+}
+
+/// A pull request to open from the pushed scaffold branch.
+#[derive(Debug, Clone, Serialize)]
+pub struct PullRequest {
+    pub title: String,
+    pub body: String,
+    /// Branch the scaffold was pushed to.
+    pub head: String,
+    /// Branch the pull request targets.
+    pub base: String,
+}
+
+/// Default template for the scaffold commit message.
+pub const COMMIT_MESSAGE_TEMPLATE: &str = "Scaffold {{ project }} with RuneWeave
+
+Plan-Hash: {{ plan_hash }}
+Seed: {{ seed }}
+Template-Hash: {{ template_hash }}
+";
+
+/// The scaffold commit to make. Unset fields fall back to git config.
+#[derive(Debug, Clone, Default)]
+pub struct ScaffoldCommit {
+    pub message: String,
+    /// Defaults to `user.name` and `user.email`, then to RuneWeave.
+    pub author: Option<GitIdentity>,
+    /// Defaults to the author.
+    pub committer: Option<GitIdentity>,
+    /// Defaults to `gpg.format` if `commit.gpgsign` is set.
+    pub sign: Option<SigningFormat>,
+    /// Defaults to `user.signingkey`; for GPG, then to the committer.
+    pub signing_key: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GitIdentity {
+    pub name: String,
+    pub email: String,
+}
+
+/// How a commit is signed, like git's `gpg.format`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SigningFormat {
+    /// OpenPGP signature made with `gpg`.
+    Gpg,
+    /// SSH signature made with `ssh-keygen -Y sign`.
+    Ssh,
+}
+
+/// Render the scaffold commit message from `template` (default
+/// [`COMMIT_MESSAGE_TEMPLATE`]) with the manifest fields, `project` and
+/// `branch`.
+pub fn commit_message(
+    template: Option<&str>,
+    project: &str,
+    branch: &str,
+    manifest: &WeaveManifest,
+) -> Result<String> {
+    let mut ctx = TeraContext::from_serialize(manifest)?;
+    ctx.insert("project", project);
+    ctx.insert("branch", branch);
+    Tera::one_off(template.unwrap_or(COMMIT_MESSAGE_TEMPLATE), &ctx, false)
+        .context("Failed to render the commit message template")
+}
+
+/// A code forge API that can open pull (or merge) requests.
+pub trait ForgeClient {
+    /// Open `pr` and return its web URL.
+    fn open_pull_request(&self, pr: &PullRequest) -> Result<String>;
+
+    /// Username and token to push over HTTPS with, if the client has a token.
+    fn push_credentials(&self) -> Option<(&str, &str)> {
+        None
+    }
+}
+
+/// GitHub REST API client.
+pub struct GitHubClient {
+    /// API root: `https://api.github.com`, or `<web_url>/api/v3` for
+    /// GitHub Enterprise.
+    pub api_url: String,
+    pub owner: String,
+    pub repo: String,
+    pub token: Option<String>,
+}
+
+impl GitHubClient {
+    /// Client for the instance at `web_url`, e.g. `https://github.com`.
+    pub fn new(web_url: &str, owner: &str, repo: &str) -> Self {
+        let api_url = match web_url {
+            "https://github.com" => "https://api.github.com".to_string(),
+            _ => format!("{web_url}/api/v3"),
+        };
+        Self {
+            api_url,
+            owner: owner.to_string(),
+            repo: repo.to_string(),
+            token: token(&["RUNEWEAVE_GIT_TOKEN", "GITHUB_TOKEN"]),
+        }
+    }
+}
+
+impl ForgeClient for GitHubClient {
+    fn open_pull_request(&self, pr: &PullRequest) -> Result<String> {
+        let url = format!(
+            "{}/repos/{}/{}/pulls",
+            self.api_url.trim_end_matches('/'),
+            self.owner,
+            self.repo
+        );
+        let auth = self.token.as_ref().map(|token| format!("Bearer {token}"));
+        let created = post_json("GitHub", &url, auth.as_deref(), pr)?;
+        response_field(&created, "html_url")
+    }
+
+    fn push_credentials(&self) -> Option<(&str, &str)> {
+        Some(("x-access-token", self.token.as_deref()?))
+    }
+}
+
+/// GitLab REST API (v4) client; opens merge requests.
+pub struct GitLabClient {
+    /// API root, `<web_url>/api/v4`.
+    pub api_url: String,
+    /// Full project path, e.g. `group/subgroup/repo`.
+    pub project: String,
+    pub token: Option<String>,
+}
+
+impl GitLabClient {
+    /// Client for the instance at `web_url`, e.g. `https://gitlab.com`.
+    pub fn new(web_url: &str, project: &str) -> Self {
+        Self {
+            api_url: format!("{web_url}/api/v4"),
+            project: project.to_string(),
+            token: token(&["RUNEWEAVE_GIT_TOKEN", "GITLAB_TOKEN"]),
+        }
+    }
+}
+
+impl ForgeClient for GitLabClient {
+    fn open_pull_request(&self, pr: &PullRequest) -> Result<String> {
+        let url = format!(
+            "{}/projects/{}/merge_requests",
+            self.api_url.trim_end_matches('/'),
+            self.project.replace('/', "%2F")
+        );
+        let auth = self.token.as_ref().map(|token| format!("Bearer {token}"));
+        let body = serde_json::json!({
+            "source_branch": pr.head,
+            "target_branch": pr.base,
+            "title": pr.title,
+            "description": pr.body,
+        });
+        let created = post_json("GitLab", &url, auth.as_deref(), &body)?;
+        response_field(&created, "web_url")
+    }
+
+    fn push_credentials(&self) -> Option<(&str, &str)> {
+        Some(("oauth2", self.token.as_deref()?))
+    }
+}
+
+/// Gitea (and Forgejo) REST API client.
+pub struct GiteaClient {
+    /// API root, `<web_url>/api/v1`.
+    pub api_url: String,
+    pub owner: String,
+    pub repo: String,
+    pub token: Option<String>,
+}
+
+impl GiteaClient {
+    /// Client for the instance at `web_url`, e.g. `https://codeberg.org`.
+    pub fn new(web_url: &str, owner: &str, repo: &str) -> Self {
+        Self {
+            api_url: format!("{web_url}/api/v1"),
+            owner: owner.to_string(),
+            repo: repo.to_string(),
+            token: token(&["RUNEWEAVE_GIT_TOKEN", "GITEA_TOKEN"]),
+        }
+    }
+}
+
+impl ForgeClient for GiteaClient {
+    fn open_pull_request(&self, pr: &PullRequest) -> Result<String> {
+        let url = format!(
+            "{}/repos/{}/{}/pulls",
+            self.api_url.trim_end_matches('/'),
+            self.owner,
+            self.repo
+        );
+        let auth = self.token.as_ref().map(|token| format!("token {token}"));
+        let created = post_json("Gitea", &url, auth.as_deref(), pr)?;
+        response_field(&created, "html_url")
+    }
+
+    fn push_credentials(&self) -> Option<(&str, &str)> {
+        Some(("runeweave", self.token.as_deref()?))
+    }
+}
+
+/// POST `body` as JSON and return the JSON response.
+#[cfg(not(target_arch = "wasm32"))]
+fn post_json(
+    forge: &str,
+    url: &str,
+    authorization: Option<&str>,
+    body: &impl Serialize,
+) -> Result<serde_json::Value> {
+    let mut request = ureq::post(url)
+        .set("Accept", "application/json")
+        .set("User-Agent", "runeweave");
+    if let Some(authorization) = authorization {
+        request = request.set("Authorization", authorization);
+    }
+
+    match request.send_json(body) {
+        Ok(response) => Ok(response.into_json()?),
+        Err(ureq::Error::Status(status, response)) => anyhow::bail!(
+            "{forge} rejected the pull request ({status}): {}",
+            response.into_string().unwrap_or_default()
+        ),
+        Err(err) => Err(err).with_context(|| format!("Failed to reach the {forge} API")),
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn post_json(
+    _forge: &str,
+    _url: &str,
+    _authorization: Option<&str>,
+    _body: &impl Serialize,
+) -> Result<serde_json::Value> {
+    anyhow::bail!("Forge API calls are not supported in WASM")
+}
+
+fn response_field(response: &serde_json::Value, field: &str) -> Result<String> {
+    response[field]
+        .as_str()
+        .map(str::to_string)
+        .with_context(|| format!("Forge response has no `{field}`: {response}"))
 }
 
 impl GitOps {
-    pub fn new(repo_spec: &str) -> Result<Self> {
-        // Parse github:owner/repo format
-        if !repo_spec.starts_with("github:") {
-            anyhow::bail!("Only github: repos are supported currently");
+    /// Parse a `--repo` value:
+    ///
+    /// - `github:owner/repo` or `github:host/owner/repo`
+    /// - `gitlab:group/subgroup/repo` or `gitlab:host/group/subgroup/repo`
+    /// - `gitea:host/owner/repo`
+    /// - a `file://`, `ssh://`, `https://` or `http://` URL, which is pushed
+    ///   to without opening a pull request
+    ///
+    /// The host (the first segment, if it contains a `.` or `:`) is where
+    /// both the branch is pushed and the forge API is called.
+    pub fn new(repo_spec: &str) -> Result<Self, WeaveError> {
+        // A malformed spec is an input error (exit code 1), not a git failure
+        let invalid = |reason: &str| {
+            WeaveError::Other(anyhow::anyhow!("Invalid repo '{repo_spec}': {reason}"))
+        };
+        let segments = |path: &str| -> Vec<String> {
+            path.trim_end_matches(".git")
+                .split('/')
+                .map(str::to_string)
+                .collect()
+        };
+        let valid = |parts: &[String]| parts.iter().all(|part| !part.is_empty());
+        // `(web URL, remaining segments)`, on `default_host` if none is given
+        let with_host = |mut parts: Vec<String>, default_host: &str| {
+            let host = match parts.first() {
+                Some(first) if first.contains(['.', ':']) => parts.remove(0),
+                _ => default_host.to_string(),
+            };
+            (format!("https://{host}"), parts)
+        };
+
+        if let Some(path) = repo_spec.strip_prefix("github:") {
+            let parts = segments(path);
+            let usage = "use github:owner/repo or github:host/owner/repo";
+            if !valid(&parts) {
+                return Err(invalid(usage));
+            }
+            let (web_url, parts) = with_host(parts, "github.com");
+            let [owner, repo] = parts.as_slice() else {
+                return Err(invalid(usage));
+            };
+            return Ok(Self {
+                remote_url: format!("{web_url}/{owner}/{repo}.git"),
+                forge: Some(Box::new(GitHubClient::new(&web_url, owner, repo))),
+            });
         }
 
-        let parts: Vec<&str> = repo_spec
-            .strip_prefix("github:")
-            .unwrap()
-            .split('/')
-            .collect();
-        if parts.len() != 2 {
-            anyhow::bail!("Invalid repo format. Use github:owner/repo");
+        if let Some(path) = repo_spec.strip_prefix("gitlab:") {
+            let parts = segments(path);
+            let (web_url, parts) = with_host(parts, "gitlab.com");
+            if parts.len() < 2 || !valid(&parts) {
+                return Err(invalid(
+                    "use gitlab:group/repo, gitlab:group/subgroup/repo or gitlab:host/group/repo",
+                ));
+            }
+            let project = parts.join("/");
+            return Ok(Self {
+                remote_url: format!("{web_url}/{project}.git"),
+                forge: Some(Box::new(GitLabClient::new(&web_url, &project))),
+            });
+        }
+
+        if let Some(path) = repo_spec.strip_prefix("gitea:") {
+            let parts = segments(path);
+            let [host, owner, repo] = parts.as_slice() else {
+                return Err(invalid("use gitea:host/owner/repo"));
+            };
+            if !valid(&parts) {
+                return Err(invalid("use gitea:host/owner/repo"));
+            }
+            let web_url = format!("https://{host}");
+            return Ok(Self {
+                remote_url: format!("{web_url}/{owner}/{repo}.git"),
+                forge: Some(Box::new(GiteaClient::new(&web_url, owner, repo))),
+            });
+        }
+
+        if ["file://", "ssh://", "https://", "http://"]
+            .iter()
+            .any(|scheme| repo_spec.starts_with(scheme))
+        {
+            return Ok(Self {
+                remote_url: repo_spec.to_string(),
+                forge: None,
+            });
         }
 
-        let repo_url = format!("https://github.com/{}/{}.git", parts[0], parts[1]);
+        Err(invalid(
+            "use github:, gitlab: or gitea:, or a file://, ssh:// or https:// URL",
+        ))
+    }
+
+    /// Commit `files` (relative to `local_path`) to a new `pr.head` branch,
+    /// push that branch and open `pr`. Returns the pull request URL, if a
+    /// forge is configured.
+    ///
+    /// If `local_path` is the root of a git repository the branch starts at
+    /// its HEAD and is checked out. Otherwise a repository is initialized
+    /// there and the branch starts at `pr.base` fetched from the remote; an
+    /// empty remote gets the scaffold as `pr.base` itself, with no pull
+    /// request.
+    pub fn push_to_repo(
+        &self,
+        local_path: &Path,
+        files: &[PathBuf],
+        commit: &ScaffoldCommit,
+        pr: &PullRequest,
+    ) -> Result<Option<String>, WeaveError> {
+        let pushed = self
+            .push_branch(local_path, files, pr, commit)
+            .map_err(WeaveError::Git)?;
+        if pushed == pr.base {
+            return Ok(None);
+        }
 
-        Ok(Self { repo_url })
+        let Some(forge) = &self.forge else {
+            return Ok(None);
+        };
+        let url = forge
+            .open_pull_request(pr)
+            .context("Failed to open pull request")
+            .map_err(WeaveError::Git)?;
+        Ok(Some(url))
     }
 
+    /// Commit and push the scaffold; returns the branch it was pushed to.
     #[cfg(not(target_arch = "wasm32"))]
-    pub fn push_to_repo(&self, local_path: &Path, branch_name: &str) -> Result<()> {
-        // Initialize git repo
-        let repo = Repository::init(local_path).context("Failed to initialize git repository")?;
+    fn push_branch(
+        &self,
+        local_path: &Path,
+        files: &[PathBuf],
+        pr: &PullRequest,
+        commit: &ScaffoldCommit,
+    ) -> Result<String> {
+        let (repo, initialized) = match find_repository(local_path)? {
+            Some(repo) => (repo, false),
+            None => (
+                Repository::init(local_path).context("Failed to initialize git repository")?,
+                true,
+            ),
+        };
+        let config = repo.config()?;
 
-        // Add all files
-        let mut index = repo.index()?;
-        index.add_all(["*"].iter(), git2::IndexAddOption::DEFAULT, None)?;
-        index.write()?;
+        // A repository we created pushes to `origin`; an existing one keeps
+        // its remotes untouched
+        let mut remote = if initialized {
+            repo.remote("origin", &self.remote_url)?
+        } else {
+            repo.remote_anonymous(&self.remote_url)?
+        };
+        let fallback = token(&["RUNEWEAVE_GIT_TOKEN"]);
+        let push_token = match &self.forge {
+            Some(forge) => forge.push_credentials(),
+            None => fallback.as_deref().map(|token| ("git", token)),
+        };
 
-        // Create initial commit
-        let sig = Signature::now("RuneWeave", "runeweave@example.com")?;
-        let tree_id = index.write_tree()?;
-        let tree = repo.find_tree(tree_id)?;
+        // Commit only the scaffold, on top of HEAD, or else of the remote's
+        // base branch so the pull request shares its history
+        let mut fetched = false;
+        let parent = match repo.head() {
+            Ok(head) => Some(head.peel_to_commit()?),
+            Err(err) if err.code() == git2::ErrorCode::UnbornBranch => {
+                let base = fetch_branch(&repo, &mut remote, &config, push_token, &pr.base)
+                    .with_context(|| {
+                        format!("Failed to fetch {} from {}", pr.base, self.remote_url)
+                    })?;
+                fetched = base.is_some();
+                base
+            }
+            Err(err) => return Err(err.into()),
+        };
+        // Nothing to branch off: the scaffold becomes the base branch
+        let branch_name = match parent {
+            Some(_) => pr.head.as_str(),
+            None => pr.base.as_str(),
+        };
 
-        let oid = repo.commit(
-            Some("HEAD"),
-            &sig,
-            &sig,
-            "Initial scaffold commit",
-            &tree,
-            &[],
-        )?;
+        let mut blobs = BTreeMap::new();
+        for file in files {
+            let oid = repo
+                .blob_path(&local_path.join(file))
+                .with_context(|| format!("Failed to add {file:?}"))?;
+            blobs.insert(file.clone(), oid);
+        }
+        let base = parent.as_ref().map(|commit| commit.tree()).transpose()?;
+        let tree = repo.find_tree(write_tree(&repo, base.as_ref(), &blobs)?)?;
 
-        // Create branch
-        repo.branch(branch_name, &repo.find_commit(oid)?, false)?;
+        let author = identity(commit.author.as_ref(), &config)?;
+        let committer = match &commit.committer {
+            Some(committer) => identity(Some(committer), &config)?,
+            None => author.clone(),
+        };
+        let parents: Vec<_> = parent.iter().collect();
+        let oid = match signing_format(commit, &config)? {
+            Some(format) => {
+                let buffer = repo.commit_create_buffer(
+                    &author,
+                    &committer,
+                    &commit.message,
+                    &tree,
+                    &parents,
+                )?;
+                let content = buffer.as_str().context("Commit is not valid UTF-8")?;
+                let key = commit
+                    .signing_key
+                    .clone()
+                    .or_else(|| config.get_string("user.signingkey").ok());
+                let signature = sign(&config, format, key.as_deref(), &committer, content)?;
+                repo.commit_signed(content, &signature, None)?
+            }
+            None => repo.commit(None, &author, &committer, &commit.message, &tree, &parents)?,
+        };
+        repo.branch(branch_name, &repo.find_commit(oid)?, false)
+            .with_context(|| format!("Failed to create branch {branch_name}"))?;
 
-        // Add remote
-        repo.remote("origin", &self.repo_url)?;
+        // Check the branch out; the worktree already matches it, so only the
+        // scaffold's index entries change and other staged changes are kept.
+        // Files that came with the fetched base branch are written out.
+        let branch_ref = format!("refs/heads/{branch_name}");
+        repo.set_head(&branch_ref)?;
+        let mut index = repo.index()?;
+        if fetched {
+            index.read_tree(&tree)?;
+        } else {
+            for path in blobs.keys() {
+                index.add_path(path)?;
+            }
+        }
+        index.write()?;
+        if fetched {
+            repo.checkout_head(Some(
+                git2::build::CheckoutBuilder::new()
+                    .safe()
+                    .recreate_missing(true),
+            ))?;
+        }
 
-        // Note: Actual push would require authentication
-        // For now, we just prepare the repo for manual push
-        println!("Repository prepared at {local_path:?}");
-        println!("To push: cd {local_path:?} && git push -u origin {branch_name}");
+        let mut rejection = None;
+        let mut callbacks = remote_callbacks(&config, push_token);
+        callbacks.push_update_reference(|_, status| {
+            rejection = status.map(str::to_string);
+            Ok(())
+        });
 
-        Ok(())
+        let mut options = PushOptions::new();
+        options.remote_callbacks(callbacks);
+        remote
+            .push(&[format!("{branch_ref}:{branch_ref}")], Some(&mut options))
+            .with_context(|| format!("Failed to push {branch_name} to {}", self.remote_url))?;
+        drop(options);
+
+        if let Some(reason) = rejection {
+            anyhow::bail!("{} rejected {branch_name}: {reason}", self.remote_url);
+        }
+        Ok(branch_name.to_string())
     }
 
     #[cfg(target_arch = "wasm32")]
-    pub fn push_to_repo(&self, _local_path: &Path, _branch_name: &str) -> Result<()> {
+    fn push_branch(
+        &self,
+        _local_path: &Path,
+        _files: &[PathBuf],
+        _pr: &PullRequest,
+        _commit: &ScaffoldCommit,
+    ) -> Result<String> {
         anyhow::bail!("Git operations are not supported in WASM")
     }
 }
+
+/// `identity`, else git's `user.name` and `user.email`, else RuneWeave.
+#[cfg(not(target_arch = "wasm32"))]
+fn identity(identity: Option<&GitIdentity>, config: &git2::Config) -> Result<Signature<'static>> {
+    if let Some(identity) = identity {
+        return Ok(Signature::now(&identity.name, &identity.email)?);
+    }
+    match (
+        config.get_string("user.name"),
+        config.get_string("user.email"),
+    ) {
+        (Ok(name), Ok(email)) => Ok(Signature::now(&name, &email)?),
+        _ => Ok(Signature::now("RuneWeave", "runeweave@example.com")?),
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn signing_format(commit: &ScaffoldCommit, config: &git2::Config) -> Result<Option<SigningFormat>> {
+    if commit.sign.is_some() || !config.get_bool("commit.gpgsign").unwrap_or(false) {
+        return Ok(commit.sign);
+    }
+    match config.get_string("gpg.format").as_deref() {
+        Ok("openpgp") | Err(_) => Ok(Some(SigningFormat::Gpg)),
+        Ok("ssh") => Ok(Some(SigningFormat::Ssh)),
+        Ok(other) => anyhow::bail!("Unsupported gpg.format '{other}'; use openpgp or ssh"),
+    }
+}
+
+/// Sign a commit buffer the way `git commit -S` does and return the armored
+/// signature.
+#[cfg(not(target_arch = "wasm32"))]
+fn sign(
+    config: &git2::Config,
+    format: SigningFormat,
+    key: Option<&str>,
+    committer: &Signature,
+    payload: &str,
+) -> Result<String> {
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+
+    // Keeps a literal SSH public key on disk until the signer has run
+    let mut public_key = None;
+    let (program, args) = match format {
+        SigningFormat::Gpg => {
+            let program = config
+                .get_string("gpg.openpgp.program")
+                .or_else(|_| config.get_string("gpg.program"))
+                .unwrap_or_else(|_| "gpg".to_string());
+            let key = key.map_or_else(|| committer.to_string(), str::to_string);
+            (
+                program,
+                vec!["--status-fd=2".to_string(), "-bsau".to_string(), key],
+            )
+        }
+        SigningFormat::Ssh => {
+            let program = config
+                .get_string("gpg.ssh.program")
+                .unwrap_or_else(|_| "ssh-keygen".to_string());
+            let key = key.context(
+                "SSH signing needs a key; set git.signing_key in the policy or user.signingkey",
+            )?;
+            let mut args: Vec<String> = ["-Y", "sign", "-n", "git", "-f"]
+                .map(str::to_string)
+                .to_vec();
+            if let Some(literal) = key.strip_prefix("key::") {
+                // The private key is in the SSH agent
+                let mut file = tempfile::NamedTempFile::new()?;
+                writeln!(file, "{literal}")?;
+                args.push(file.path().to_string_lossy().into_owned());
+                args.push("-U".to_string());
+                public_key = Some(file);
+            } else if let (Some(rest), Ok(home)) = (key.strip_prefix("~/"), std::env::var("HOME")) {
+                args.push(Path::new(&home).join(rest).to_string_lossy().into_owned());
+            } else {
+                args.push(key.to_string());
+            }
+            (program, args)
+        }
+    };
+
+    let mut child = Command::new(&program)
+        .args(&args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to run {program} to sign the commit"))?;
+    // Signers read all of their input before writing anything
+    child
+        .stdin
+        .take()
+        .expect("stdin is piped")
+        .write_all(payload.as_bytes())?;
+    let output = child.wait_with_output()?;
+    drop(public_key);
+
+    if !output.status.success() {
+        anyhow::bail!(
+            "{program} failed to sign the commit: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    String::from_utf8(output.stdout).context("Commit signature is not valid UTF-8")
+}
+
+/// Fail if `out_dir` is inside a git repository with uncommitted changes,
+/// which the scaffold would otherwise overwrite or be committed next to.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn ensure_clean(out_dir: &Path) -> Result<(), WeaveError> {
+    let check = || -> Result<()> {
+        let Some(repo) = find_repository(out_dir)? else {
+            return Ok(());
+        };
+        let mut options = StatusOptions::new();
+        options.include_untracked(true).include_ignored(false);
+        let statuses = repo.statuses(Some(&mut options))?;
+        if statuses.is_empty() {
+            return Ok(());
+        }
+
+        let mut paths: Vec<_> = statuses
+            .iter()
+            .take(5)
+            .filter_map(|entry| entry.path().map(str::to_string))
+            .collect();
+        if statuses.len() > paths.len() {
+            paths.push(format!("and {} more", statuses.len() - paths.len()));
+        }
+        anyhow::bail!(
+            "{:?} has uncommitted changes ({}); commit or stash them, or pass --force",
+            repo.workdir().unwrap_or(repo.path()),
+            paths.join(", ")
+        )
+    };
+    check().map_err(WeaveError::Git)
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn ensure_clean(_out_dir: &Path) -> Result<(), WeaveError> {
+    Ok(())
+}
+
+/// The non-bare repository whose working directory is `path`, if any.
+///
+/// Repositories further up are not considered: an output directory inside
+/// another project's checkout gets a repository of its own.
+#[cfg(not(target_arch = "wasm32"))]
+fn find_repository(path: &Path) -> Result<Option<Repository>> {
+    let repo = match Repository::open(path) {
+        Ok(repo) => repo,
+        Err(err) if err.code() == git2::ErrorCode::NotFound => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+    match repo.workdir() {
+        Some(workdir) if workdir.canonicalize()? == path.canonicalize()? => Ok(Some(repo)),
+        _ => Ok(None),
+    }
+}
+
+/// Write `base` with `blobs` (by path relative to it) added or replaced.
+#[cfg(not(target_arch = "wasm32"))]
+fn write_tree(
+    repo: &Repository,
+    base: Option<&Tree>,
+    blobs: &BTreeMap<PathBuf, Oid>,
+) -> Result<Oid> {
+    let mut builder = repo.treebuilder(base)?;
+    let mut dirs: BTreeMap<String, BTreeMap<PathBuf, Oid>> = BTreeMap::new();
+    for (path, oid) in blobs {
+        let mut components = path.components();
+        let name = components
+            .next()
+            .and_then(|c| c.as_os_str().to_str())
+            .with_context(|| format!("Cannot commit {path:?}"))?;
+        let rest = components.as_path();
+        if rest.as_os_str().is_empty() {
+            builder.insert(name, *oid, 0o100644)?;
+        } else {
+            dirs.entry(name.to_string())
+                .or_default()
+                .insert(rest.to_path_buf(), *oid);
+        }
+    }
+
+    for (name, blobs) in dirs {
+        let subtree = match base.and_then(|tree| tree.get_name(&name)) {
+            Some(entry) if entry.kind() == Some(ObjectType::Tree) => {
+                Some(entry.to_object(repo)?.peel_to_tree()?)
+            }
+            _ => None,
+        };
+        let oid = write_tree(repo, subtree.as_ref(), &blobs)?;
+        builder.insert(&name, oid, 0o040000)?;
+    }
+    Ok(builder.write()?)
+}
+
+/// Fetch `branch` from `remote`, or `None` if the remote has no such branch.
+#[cfg(not(target_arch = "wasm32"))]
+fn fetch_branch<'r>(
+    repo: &'r Repository,
+    remote: &mut git2::Remote,
+    config: &git2::Config,
+    token: Option<(&str, &str)>,
+    branch: &str,
+) -> Result<Option<git2::Commit<'r>>> {
+    let fetch_head = repo.path().join("FETCH_HEAD");
+    if fetch_head.exists() {
+        std::fs::remove_file(&fetch_head)?;
+    }
+    let branch_ref = format!("refs/heads/{branch}");
+    let mut options = git2::FetchOptions::new();
+    options.remote_callbacks(remote_callbacks(config, token));
+    remote.fetch(&[&branch_ref], Some(&mut options), None)?;
+    if !fetch_head.exists() {
+        return Ok(None);
+    }
+
+    let mut found = None;
+    repo.fetchhead_foreach(|name, _, oid, _| {
+        if name == branch_ref {
+            found = Some(*oid);
+        }
+        true
+    })?;
+    found
+        .map(|oid| repo.find_commit(oid))
+        .transpose()
+        .map_err(Into::into)
+}
+
+/// Callbacks that authenticate with [`credentials`].
+#[cfg(not(target_arch = "wasm32"))]
+fn remote_callbacks<'a>(
+    config: &'a git2::Config,
+    token: Option<(&'a str, &'a str)>,
+) -> RemoteCallbacks<'a> {
+    let mut attempts = 0;
+    let mut callbacks = RemoteCallbacks::new();
+    callbacks.credentials(move |url, username, allowed| {
+        attempts += 1;
+        credentials(config, token, url, username, allowed, attempts)
+    });
+    callbacks
+}
+
+/// Credentials for a push: the forge token (or `RUNEWEAVE_GIT_TOKEN`) for
+/// HTTPS, the SSH agent for SSH, and git's credential helpers as a fallback.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn credentials(
+    config: &git2::Config,
+    token: Option<(&str, &str)>,
+    url: &str,
+    username: Option<&str>,
+    allowed: CredentialType,
+    attempt: u32,
+) -> Result<Cred, git2::Error> {
+    // libgit2 keeps asking while the remote rejects what it was given
+    if attempt > 3 {
+        return Err(git2::Error::from_str(
+            "authentication failed; set RUNEWEAVE_GIT_TOKEN, load an SSH key into \
+             the agent or configure a git credential helper",
+        ));
+    }
+
+    // Asked first for SSH remotes that name no user, before any key
+    if allowed.contains(CredentialType::USERNAME) {
+        return Cred::username(username.unwrap_or("git"));
+    }
+    if allowed.contains(CredentialType::SSH_KEY) {
+        return Cred::ssh_key_from_agent(username.unwrap_or("git"));
+    }
+    if allowed.contains(CredentialType::USER_PASS_PLAINTEXT) {
+        if let (Some((user, token)), 1) = (token, attempt) {
+            return Cred::userpass_plaintext(username.unwrap_or(user), token);
+        }
+        return Cred::credential_helper(config, url, username);
+    }
+    Cred::default()
+}
+
+/// The first non-empty variable of `vars`.
+fn token(vars: &[&str]) -> Option<String> {
+    vars.iter()
+        .find_map(|var| std::env::var(var).ok().filter(|token| !token.is_empty()))
+}
@@ -1,19 +1,74 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::borrow::Cow;
 use std::path::Path;
 use tera::{Context as TeraContext, Tera};
 
+use crate::build_check::CheckCommand;
+use crate::dependency::{Dependency, Ecosystem};
+use crate::plugin;
+use crate::render::OutputTree;
 use crate::verify::{Language, Service};
 
+/// A framework a language pack can render and the runtimes it can target.
+#[derive(Debug, Clone, Serialize)]
+pub struct Capability {
+    pub framework: Cow<'static, str>,
+    /// Supported runtimes; the first one is used when the plan sets none.
+    pub runtimes: Vec<Cow<'static, str>>,
+}
+
+impl Capability {
+    pub fn new<S: Into<Cow<'static, str>>>(
+        framework: impl Into<Cow<'static, str>>,
+        runtimes: impl IntoIterator<Item = S>,
+    ) -> Self {
+        Self {
+            framework: framework.into(),
+            runtimes: runtimes.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
 pub trait LanguagePack {
+    fn language(&self) -> Language;
+
+    /// Frameworks this pack can render; the first one is used when the plan
+    /// sets none.
+    fn capabilities(&self) -> Vec<Capability>;
+
     fn render_service(
         &self,
         service: &Service,
-        out_dir: &Path,
+        out: &mut OutputTree,
         tera: &mut Tera,
         ctx: &TeraContext,
     ) -> Result<()>;
 
-    fn register_templates(&self, tera: &mut Tera) -> Result<()>;
+    /// Template sources this pack renders, as `(name, source)` pairs.
+    fn templates(&self) -> Vec<(&'static str, &'static str)>;
+
+    fn register_templates(&self, tera: &mut Tera) -> Result<()> {
+        tera.add_raw_templates(self.templates())?;
+        Ok(())
+    }
+
+    /// Port a service listens on when the plan does not set one and no other
+    /// service has claimed it.
+    fn default_port(&self) -> u16;
+
+    /// Dependencies this pack's templates write into the service manifest.
+    fn dependencies(&self, service: &Service) -> Vec<Dependency>;
+
+    /// Offline-capable commands that check a generated service, run in its
+    /// directory by `apply --build-check`.
+    fn check_commands(&self, service: &Service) -> Vec<CheckCommand>;
+
+    /// Registry `Service.dependencies` are resolved from. Without one they
+    /// are passed through unchecked.
+    fn ecosystem(&self) -> Option<Ecosystem> {
+        Ecosystem::for_language(&self.language())
+    }
 }
 
 pub struct RustPack;
@@ -21,35 +76,46 @@ pub struct NodePack;
 pub struct PythonPack;
 pub struct GoPack;
 pub struct JavaPack;
+pub struct DotNetPack;
+pub struct DenoPack;
 
 impl LanguagePack for RustPack {
-    fn register_templates(&self, tera: &mut Tera) -> Result<()> {
-        // Rust Cargo.toml template
-        tera.add_raw_template(
-            "rust-cargo.toml",
-            r#"[package]
+    fn language(&self) -> Language {
+        Language::Rust
+    }
+
+    fn capabilities(&self) -> Vec<Capability> {
+        vec![
+            Capability::new("actix", ["native"]),
+            Capability::new("worker", ["cloudflare"]),
+        ]
+    }
+
+    fn templates(&self) -> Vec<(&'static str, &'static str)> {
+        vec![
+            // Rust Cargo.toml template
+            (
+                "rust-cargo.toml",
+                r#"[package]
 name = "{{ service_name }}"
 version = "0.1.0"
 edition = "2021"
 rust-version = "{{ rust_version }}"
 
 [dependencies]
-{%- if framework == "actix" %}
-actix-web = "4"
-{%- elif framework == "worker" %}
-worker = "0.6"
+{%- for dep in dependencies | filter(attribute="dev", value=false) %}
+{%- if dep.features %}
+{{ dep.name }} = { version = "{{ dep.version }}", features = [{% for feature in dep.features %}"{{ feature }}"{% if not loop.last %}, {% endif %}{% endfor %}] }
+{%- else %}
+{{ dep.name }} = "{{ dep.version }}"
 {%- endif %}
-serde = { version = "1.0", features = ["derive"] }
-serde_json = "1.0"
-tracing = "0.1"
-anyhow = "1.0"
+{%- endfor %}
 "#,
-        )?;
-
-        // Rust main.rs for Actix
-        tera.add_raw_template(
-            "rust-actix-main.rs",
-            r#"use actix_web::{web, App, HttpResponse, HttpServer};
+            ),
+            // Rust main.rs for Actix
+            (
+                "rust-actix-main.rs",
+                r#"use actix_web::{web, App, HttpResponse, HttpServer};
 use tracing::info;
 
 async fn healthz() -> HttpResponse {
@@ -62,23 +128,22 @@ async fn healthz() -> HttpResponse {
 async fn main() -> std::io::Result<()> {
     tracing_subscriber::fmt::init();
     
-    info!("Starting {{ service_name }} server on 0.0.0.0:8080");
+    info!("Starting {{ service_name }} server on 0.0.0.0:{{ service_port }}");
     
     HttpServer::new(|| {
         App::new()
             .route("/healthz", web::get().to(healthz))
     })
-    .bind("0.0.0.0:8080")?
+    .bind("0.0.0.0:{{ service_port }}")?
     .run()
     .await
 }
 "#,
-        )?;
-
-        // Rust lib.rs for Workers
-        tera.add_raw_template(
-            "rust-worker-lib.rs",
-            r#"use worker::*;
+            ),
+            // Rust lib.rs for Workers
+            (
+                "rust-worker-lib.rs",
+                r#"use worker::*;
 
 #[event(fetch)]
 async fn main(req: Request, env: Env, _ctx: Context) -> Result<Response> {
@@ -92,20 +157,50 @@ async fn main(req: Request, env: Env, _ctx: Context) -> Result<Response> {
         .await
 }
 "#,
-        )?;
+            ),
+        ]
+    }
 
-        Ok(())
+    fn default_port(&self) -> u16 {
+        8080
+    }
+
+    fn check_commands(&self, service: &Service) -> Vec<CheckCommand> {
+        match service.framework.as_deref() {
+            Some("worker") => vec![CheckCommand::new(
+                "cargo",
+                &["check", "--offline", "--target", "wasm32-unknown-unknown"],
+            )],
+            _ => vec![CheckCommand::new("cargo", &["check", "--offline"])],
+        }
+    }
+
+    fn dependencies(&self, service: &Service) -> Vec<Dependency> {
+        let mut deps = Vec::new();
+        match service.framework.as_deref() {
+            Some("worker") => deps.push(Dependency::new(Ecosystem::Crates, "worker", "0.6")),
+            _ => deps.extend([
+                Dependency::new(Ecosystem::Crates, "actix-web", "4"),
+                Dependency::new(Ecosystem::Crates, "tracing-subscriber", "0.3"),
+            ]),
+        }
+        deps.extend([
+            Dependency::new(Ecosystem::Crates, "serde", "1.0").with_features(&["derive"]),
+            Dependency::new(Ecosystem::Crates, "serde_json", "1.0"),
+            Dependency::new(Ecosystem::Crates, "tracing", "0.1"),
+            Dependency::new(Ecosystem::Crates, "anyhow", "1.0"),
+        ]);
+        deps
     }
 
     fn render_service(
         &self,
         service: &Service,
-        out_dir: &Path,
+        out: &mut OutputTree,
         tera: &mut Tera,
         ctx: &TeraContext,
     ) -> Result<()> {
-        let service_dir = out_dir.join("services").join(&service.name);
-        std::fs::create_dir_all(&service_dir)?;
+        let service_dir = Path::new("services").join(&service.name);
 
         let mut service_ctx = ctx.clone();
         service_ctx.insert("service_name", &service.name);
@@ -113,32 +208,28 @@ async fn main(req: Request, env: Env, _ctx: Context) -> Result<Response> {
             "framework",
             &service.framework.as_deref().unwrap_or("actix"),
         );
+        service_ctx.insert("dependencies", &service_dependencies(self, service)?);
 
-        // Add rust_version from toolchain config
-        if let Some(toolchain) = ctx.get("toolchain") {
-            if let Some(rust_toolchain) = toolchain.get("rust") {
-                if let Some(version) = rust_toolchain.get("version") {
-                    service_ctx.insert("rust_version", version);
-                }
-            }
-        }
+        service_ctx.insert(
+            "rust_version",
+            &toolchain_version(ctx, "rust").unwrap_or_else(|| "1.82".to_string()),
+        );
 
         // Cargo.toml
         let content = tera.render("rust-cargo.toml", &service_ctx)?;
-        std::fs::write(service_dir.join("Cargo.toml"), content)?;
+        out.write(service_dir.join("Cargo.toml"), content)?;
 
         // src/main.rs or src/lib.rs
         let src_dir = service_dir.join("src");
-        std::fs::create_dir_all(&src_dir)?;
 
         match service.framework.as_deref() {
             Some("worker") => {
                 let content = tera.render("rust-worker-lib.rs", &service_ctx)?;
-                std::fs::write(src_dir.join("lib.rs"), content)?;
+                out.write(src_dir.join("lib.rs"), content)?;
             }
             _ => {
                 let content = tera.render("rust-actix-main.rs", &service_ctx)?;
-                std::fs::write(src_dir.join("main.rs"), content)?;
+                out.write(src_dir.join("main.rs"), content)?;
             }
         }
 
@@ -147,11 +238,23 @@ async fn main(req: Request, env: Env, _ctx: Context) -> Result<Response> {
 }
 
 impl LanguagePack for NodePack {
-    fn register_templates(&self, tera: &mut Tera) -> Result<()> {
-        // Node package.json
-        tera.add_raw_template(
-            "node-package.json",
-            r#"{
+    fn language(&self) -> Language {
+        Language::Node
+    }
+
+    fn capabilities(&self) -> Vec<Capability> {
+        vec![
+            Capability::new("fastify", ["node"]),
+            Capability::new("hono", ["cloudflare"]),
+        ]
+    }
+
+    fn templates(&self) -> Vec<(&'static str, &'static str)> {
+        vec![
+            // Node package.json
+            (
+                "node-package.json",
+                r#"{
   "name": "{{ service_name }}",
   "version": "0.1.0",
   "type": "module",
@@ -163,29 +266,22 @@ impl LanguagePack for NodePack {
     "lint": "eslint src"
   },
   "dependencies": {
-{%- if framework == "fastify" %}
-    "fastify": "^4.0.0",
-{%- elif framework == "hono" and runtime == "cloudflare" %}
-    "@cloudflare/workers-types": "^4.0.0",
-    "hono": "^3.0.0",
-{%- endif %}
-    "zod": "^3.0.0"
+{%- for dep in dependencies | filter(attribute="dev", value=false) %}
+    "{{ dep.name }}": "{{ dep.version }}"{% if not loop.last %},{% endif %}
+{%- endfor %}
   },
   "devDependencies": {
-    "@types/node": "^20.0.0",
-    "eslint": "^8.0.0",
-    "tsx": "^4.0.0",
-    "typescript": "^5.0.0",
-    "vitest": "^1.0.0"
+{%- for dep in dependencies | filter(attribute="dev", value=true) %}
+    "{{ dep.name }}": "{{ dep.version }}"{% if not loop.last %},{% endif %}
+{%- endfor %}
   }
 }
 "#,
-        )?;
-
-        // TypeScript index.ts
-        tera.add_raw_template(
-            "node-index.ts",
-            r#"{% if framework == "fastify" %}
+            ),
+            // TypeScript index.ts
+            (
+                "node-index.ts",
+                r#"{% if framework == "fastify" %}
 import Fastify from 'fastify'
 
 const app = Fastify({ logger: true })
@@ -196,7 +292,7 @@ app.get('/healthz', async () => {
 
 const start = async () => {
   try {
-    await app.listen({ port: 3000, host: '0.0.0.0' })
+    await app.listen({ port: {{ service_port }}, host: '0.0.0.0' })
   } catch (err) {
     app.log.error(err)
     process.exit(1)
@@ -216,12 +312,11 @@ app.get('/healthz', (c) => {
 export default app
 {% endif %}
 "#,
-        )?;
-
-        // tsconfig.json
-        tera.add_raw_template(
-            "node-tsconfig.json",
-            r#"{
+            ),
+            // tsconfig.json
+            (
+                "node-tsconfig.json",
+                r#"{
   "compilerOptions": {
     "target": "ES2022",
     "module": "ESNext",
@@ -236,20 +331,62 @@ export default app
   "exclude": ["node_modules", "dist"]
 }
 "#,
-        )?;
+            ),
+        ]
+    }
 
-        Ok(())
+    fn default_port(&self) -> u16 {
+        3000
+    }
+
+    fn check_commands(&self, _service: &Service) -> Vec<CheckCommand> {
+        vec![
+            CheckCommand::new(
+                "npm",
+                &[
+                    "install",
+                    "--prefer-offline",
+                    "--ignore-scripts",
+                    "--no-audit",
+                    "--no-fund",
+                ],
+            ),
+            CheckCommand::new("npm", &["run", "build"]),
+        ]
+    }
+
+    fn dependencies(&self, service: &Service) -> Vec<Dependency> {
+        let mut deps = Vec::new();
+        match (
+            service.framework.as_deref().unwrap_or("fastify"),
+            service.runtime.as_deref(),
+        ) {
+            ("fastify", _) => deps.push(Dependency::new(Ecosystem::Npm, "fastify", "^4.0.0")),
+            ("hono", Some("cloudflare")) => deps.extend([
+                Dependency::new(Ecosystem::Npm, "@cloudflare/workers-types", "^4.0.0"),
+                Dependency::new(Ecosystem::Npm, "hono", "^3.0.0"),
+            ]),
+            _ => {}
+        }
+        deps.extend([
+            Dependency::new(Ecosystem::Npm, "zod", "^3.0.0"),
+            Dependency::new(Ecosystem::Npm, "@types/node", "^20.0.0").dev(),
+            Dependency::new(Ecosystem::Npm, "eslint", "^8.0.0").dev(),
+            Dependency::new(Ecosystem::Npm, "tsx", "^4.0.0").dev(),
+            Dependency::new(Ecosystem::Npm, "typescript", "^5.0.0").dev(),
+            Dependency::new(Ecosystem::Npm, "vitest", "^1.0.0").dev(),
+        ]);
+        deps
     }
 
     fn render_service(
         &self,
         service: &Service,
-        out_dir: &Path,
+        out: &mut OutputTree,
         tera: &mut Tera,
         ctx: &TeraContext,
     ) -> Result<()> {
-        let service_dir = out_dir.join("services").join(&service.name);
-        std::fs::create_dir_all(&service_dir)?;
+        let service_dir = Path::new("services").join(&service.name);
 
         let mut service_ctx = ctx.clone();
         service_ctx.insert("service_name", &service.name);
@@ -258,57 +395,67 @@ export default app
             &service.framework.as_deref().unwrap_or("fastify"),
         );
         service_ctx.insert("runtime", &service.runtime.as_deref().unwrap_or("node"));
+        service_ctx.insert("dependencies", &service_dependencies(self, service)?);
 
         // package.json
         let content = tera.render("node-package.json", &service_ctx)?;
-        std::fs::write(service_dir.join("package.json"), content)?;
+        out.write(service_dir.join("package.json"), content)?;
 
         // tsconfig.json
         let content = tera.render("node-tsconfig.json", &service_ctx)?;
-        std::fs::write(service_dir.join("tsconfig.json"), content)?;
+        out.write(service_dir.join("tsconfig.json"), content)?;
 
         // src/index.ts
         let src_dir = service_dir.join("src");
-        std::fs::create_dir_all(&src_dir)?;
         let content = tera.render("node-index.ts", &service_ctx)?;
-        std::fs::write(src_dir.join("index.ts"), content)?;
+        out.write(src_dir.join("index.ts"), content)?;
 
         Ok(())
     }
 }
 
 impl LanguagePack for PythonPack {
-    fn register_templates(&self, tera: &mut Tera) -> Result<()> {
-        // Python pyproject.toml
-        tera.add_raw_template(
-            "python-pyproject.toml",
-            r#"[tool.poetry]
+    fn language(&self) -> Language {
+        Language::Python
+    }
+
+    fn capabilities(&self) -> Vec<Capability> {
+        vec![
+            Capability::new("none", ["python"]),
+            Capability::new("fastapi", ["python"]),
+        ]
+    }
+
+    fn templates(&self) -> Vec<(&'static str, &'static str)> {
+        vec![
+            // Python pyproject.toml
+            (
+                "python-pyproject.toml",
+                r#"[tool.poetry]
 name = "{{ service_name }}"
 version = "0.1.0"
 description = ""
 
 [tool.poetry.dependencies]
 python = "^{{ python_version }}"
-{%- if framework == "fastapi" %}
-fastapi = "^0.100.0"
-uvicorn = "^0.30.0"
-{%- endif %}
-pydantic = "^2.0.0"
+{%- for dep in dependencies | filter(attribute="dev", value=false) %}
+{{ dep.name }} = "{{ dep.version }}"
+{%- endfor %}
 
 [tool.poetry.group.dev.dependencies]
-pytest = "^8.0.0"
-ruff = "^0.5.0"
+{%- for dep in dependencies | filter(attribute="dev", value=true) %}
+{{ dep.name }} = "{{ dep.version }}"
+{%- endfor %}
 
 [build-system]
 requires = ["poetry-core"]
 build-backend = "poetry.core.masonry.api"
 "#,
-        )?;
-
-        // Python main.py
-        tera.add_raw_template(
-            "python-main.py",
-            r#"{% if framework == "fastapi" %}
+            ),
+            // Python main.py
+            (
+                "python-main.py",
+                r#"{% if framework == "fastapi" %}
 from fastapi import FastAPI
 from pydantic import BaseModel
 
@@ -323,7 +470,7 @@ def health_check():
 
 if __name__ == "__main__":
     import uvicorn
-    uvicorn.run(app, host="0.0.0.0", port=8000)
+    uvicorn.run(app, host="0.0.0.0", port={{ service_port }})
 {% else %}
 def main():
     print("{{ service_name }} started")
@@ -332,68 +479,102 @@ if __name__ == "__main__":
     main()
 {% endif %}
 "#,
-        )?;
+            ),
+        ]
+    }
 
-        Ok(())
+    fn default_port(&self) -> u16 {
+        8000
+    }
+
+    fn check_commands(&self, _service: &Service) -> Vec<CheckCommand> {
+        vec![CheckCommand::new("pytest", &["--collect-only", "-q"])]
+    }
+
+    fn dependencies(&self, service: &Service) -> Vec<Dependency> {
+        let mut deps = Vec::new();
+        if service.framework.as_deref() == Some("fastapi") {
+            deps.extend([
+                Dependency::new(Ecosystem::PyPI, "fastapi", "^0.100.0"),
+                Dependency::new(Ecosystem::PyPI, "uvicorn", "^0.30.0"),
+            ]);
+        }
+        deps.extend([
+            Dependency::new(Ecosystem::PyPI, "pydantic", "^2.0.0"),
+            Dependency::new(Ecosystem::PyPI, "pytest", "^8.0.0").dev(),
+            Dependency::new(Ecosystem::PyPI, "ruff", "^0.5.0").dev(),
+        ]);
+        deps
     }
 
     fn render_service(
         &self,
         service: &Service,
-        out_dir: &Path,
+        out: &mut OutputTree,
         tera: &mut Tera,
         ctx: &TeraContext,
     ) -> Result<()> {
-        let service_dir = out_dir.join("services").join(&service.name);
-        std::fs::create_dir_all(&service_dir)?;
+        let service_dir = Path::new("services").join(&service.name);
 
         let mut service_ctx = ctx.clone();
         service_ctx.insert("service_name", &service.name);
         service_ctx.insert("framework", &service.framework.as_deref().unwrap_or("none"));
-        service_ctx.insert("python_version", &"3.12");
+        service_ctx.insert("dependencies", &service_dependencies(self, service)?);
+        service_ctx.insert(
+            "python_version",
+            &toolchain_version(ctx, "python").unwrap_or_else(|| "3.12".to_string()),
+        );
 
         // pyproject.toml
         let content = tera.render("python-pyproject.toml", &service_ctx)?;
-        std::fs::write(service_dir.join("pyproject.toml"), content)?;
+        out.write(service_dir.join("pyproject.toml"), content)?;
 
         // src/main.py
         let src_dir = service_dir.join("src");
-        std::fs::create_dir_all(&src_dir)?;
         let content = tera.render("python-main.py", &service_ctx)?;
-        std::fs::write(src_dir.join("main.py"), content)?;
+        out.write(src_dir.join("main.py"), content)?;
 
         // tests/__init__.py
         let tests_dir = service_dir.join("tests");
-        std::fs::create_dir_all(&tests_dir)?;
-        std::fs::write(tests_dir.join("__init__.py"), "")?;
+        out.write(tests_dir.join("__init__.py"), "")?;
 
         Ok(())
     }
 }
 
 impl LanguagePack for GoPack {
-    fn register_templates(&self, tera: &mut Tera) -> Result<()> {
-        // Go go.mod
-        tera.add_raw_template(
-            "go-mod",
-            r#"module {{ service_name }}
+    fn language(&self) -> Language {
+        Language::Go
+    }
+
+    fn capabilities(&self) -> Vec<Capability> {
+        vec![
+            Capability::new("stdlib", ["native"]),
+            Capability::new("gin", ["native"]),
+            Capability::new("fiber", ["native"]),
+        ]
+    }
+
+    fn templates(&self) -> Vec<(&'static str, &'static str)> {
+        vec![
+            // Go go.mod
+            (
+                "go-mod",
+                r#"module {{ service_name }}
 
 go {{ go_version }}
 
 require (
-{%- if framework == "gin" %}
-    github.com/gin-gonic/gin v1.9.1
-{%- elif framework == "fiber" %}
-    github.com/gofiber/fiber/v2 v2.52.0
-{%- endif %}
+{%- for dep in dependencies %}
+    {{ dep.name }} {{ dep.version }}
+{%- endfor %}
 )
 "#,
-        )?;
-
-        // Go main.go
-        tera.add_raw_template(
-            "go-main.go",
-            r#"package main
+            ),
+            // Go main.go
+            (
+                "go-main.go",
+                r#"package main
 
 {% if framework == "gin" %}
 import (
@@ -410,7 +591,7 @@ func main() {
         })
     })
     
-    r.Run(":8080")
+    r.Run(":{{ service_port }}")
 }
 {% elif framework == "fiber" %}
 import (
@@ -426,7 +607,7 @@ func main() {
         })
     })
     
-    app.Listen(":8080")
+    app.Listen(":{{ service_port }}")
 }
 {% else %}
 import (
@@ -441,25 +622,47 @@ func healthHandler(w http.ResponseWriter, r *http.Request) {
 
 func main() {
     http.HandleFunc("/healthz", healthHandler)
-    fmt.Println("Server starting on :8080")
-    http.ListenAndServe(":8080", nil)
+    fmt.Println("Server starting on :{{ service_port }}")
+    http.ListenAndServe(":{{ service_port }}", nil)
 }
 {% endif %}
 "#,
-        )?;
+            ),
+        ]
+    }
 
-        Ok(())
+    fn default_port(&self) -> u16 {
+        8080
+    }
+
+    fn check_commands(&self, _service: &Service) -> Vec<CheckCommand> {
+        vec![CheckCommand::new("go", &["build", "-n", "./..."])]
+    }
+
+    fn dependencies(&self, service: &Service) -> Vec<Dependency> {
+        match service.framework.as_deref() {
+            Some("gin") => vec![Dependency::new(
+                Ecosystem::Go,
+                "github.com/gin-gonic/gin",
+                "v1.9.1",
+            )],
+            Some("fiber") => vec![Dependency::new(
+                Ecosystem::Go,
+                "github.com/gofiber/fiber/v2",
+                "v2.52.0",
+            )],
+            _ => Vec::new(),
+        }
     }
 
     fn render_service(
         &self,
         service: &Service,
-        out_dir: &Path,
+        out: &mut OutputTree,
         tera: &mut Tera,
         ctx: &TeraContext,
     ) -> Result<()> {
-        let service_dir = out_dir.join("services").join(&service.name);
-        std::fs::create_dir_all(&service_dir)?;
+        let service_dir = Path::new("services").join(&service.name);
 
         let mut service_ctx = ctx.clone();
         service_ctx.insert("service_name", &service.name);
@@ -467,26 +670,39 @@ func main() {
             "framework",
             &service.framework.as_deref().unwrap_or("stdlib"),
         );
-        service_ctx.insert("go_version", &"1.22");
+        service_ctx.insert("dependencies", &service_dependencies(self, service)?);
+        service_ctx.insert(
+            "go_version",
+            &toolchain_version(ctx, "go").unwrap_or_else(|| "1.22".to_string()),
+        );
 
         // go.mod
         let content = tera.render("go-mod", &service_ctx)?;
-        std::fs::write(service_dir.join("go.mod"), content)?;
+        out.write(service_dir.join("go.mod"), content)?;
 
         // main.go
         let content = tera.render("go-main.go", &service_ctx)?;
-        std::fs::write(service_dir.join("main.go"), content)?;
+        out.write(service_dir.join("main.go"), content)?;
 
         Ok(())
     }
 }
 
 impl LanguagePack for JavaPack {
-    fn register_templates(&self, tera: &mut Tera) -> Result<()> {
-        // Java pom.xml
-        tera.add_raw_template(
-            "java-pom.xml",
-            r#"<?xml version="1.0" encoding="UTF-8"?>
+    fn language(&self) -> Language {
+        Language::Java
+    }
+
+    fn capabilities(&self) -> Vec<Capability> {
+        vec![Capability::new("spring", ["jvm"])]
+    }
+
+    fn templates(&self) -> Vec<(&'static str, &'static str)> {
+        vec![
+            // Java pom.xml
+            (
+                "java-pom.xml",
+                r#"<?xml version="1.0" encoding="UTF-8"?>
 <project xmlns="http://maven.apache.org/POM/4.0.0"
          xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance"
          xsi:schemaLocation="http://maven.apache.org/POM/4.0.0 
@@ -498,42 +714,92 @@ impl LanguagePack for JavaPack {
     <version>0.1.0</version>
     
     <properties>
-        <maven.compiler.source>{{ java_version }}</maven.compiler.source>
-        <maven.compiler.target>{{ java_version }}</maven.compiler.target>
+        <maven.compiler.source>{{ java_version | split(pat=".") | first }}</maven.compiler.source>
+        <maven.compiler.target>{{ java_version | split(pat=".") | first }}</maven.compiler.target>
         <project.build.sourceEncoding>UTF-8</project.build.sourceEncoding>
     </properties>
     
     <dependencies>
-{%- if framework == "spring" %}
+{%- for dep in dependencies %}
         <dependency>
-            <groupId>org.springframework.boot</groupId>
-            <artifactId>spring-boot-starter-web</artifactId>
-            <version>3.2.0</version>
-        </dependency>
-{%- endif %}
-        <dependency>
-            <groupId>junit</groupId>
-            <artifactId>junit</artifactId>
-            <version>4.13.2</version>
+            <groupId>{{ dep.name | split(pat=":") | first }}</groupId>
+            <artifactId>{{ dep.name | split(pat=":") | last }}</artifactId>
+            <version>{{ dep.version }}</version>
+{%- if dep.dev %}
             <scope>test</scope>
+{%- endif %}
         </dependency>
+{%- endfor %}
     </dependencies>
 </project>
 "#,
-        )?;
+            ),
+            // Java Application.java
+            (
+                "java-application.java",
+                r#"package com.example;
 
-        Ok(())
+import org.springframework.boot.SpringApplication;
+import org.springframework.boot.autoconfigure.SpringBootApplication;
+import org.springframework.web.bind.annotation.GetMapping;
+import org.springframework.web.bind.annotation.RestController;
+
+@SpringBootApplication
+@RestController
+public class Application {
+    
+    public static void main(String[] args) {
+        SpringApplication.run(Application.class, args);
+    }
+    
+    @GetMapping("/healthz")
+    public String healthz() {
+        return "{\"status\":\"healthy\"}";
+    }
+}
+"#,
+            ),
+            // Java application.properties
+            (
+                "java-application.properties",
+                r#"server.port={{ service_port }}
+"#,
+            ),
+        ]
+    }
+
+    fn default_port(&self) -> u16 {
+        8080
+    }
+
+    fn check_commands(&self, _service: &Service) -> Vec<CheckCommand> {
+        vec![CheckCommand::new(
+            "mvn",
+            &["--offline", "--quiet", "compile"],
+        )]
+    }
+
+    fn dependencies(&self, service: &Service) -> Vec<Dependency> {
+        let mut deps = Vec::new();
+        if service.framework.as_deref().unwrap_or("spring") == "spring" {
+            deps.push(Dependency::new(
+                Ecosystem::Maven,
+                "org.springframework.boot:spring-boot-starter-web",
+                "3.2.0",
+            ));
+        }
+        deps.push(Dependency::new(Ecosystem::Maven, "junit:junit", "4.13.2").dev());
+        deps
     }
 
     fn render_service(
         &self,
         service: &Service,
-        out_dir: &Path,
+        out: &mut OutputTree,
         tera: &mut Tera,
         ctx: &TeraContext,
     ) -> Result<()> {
-        let service_dir = out_dir.join("services").join(&service.name);
-        std::fs::create_dir_all(&service_dir)?;
+        let service_dir = Path::new("services").join(&service.name);
 
         let mut service_ctx = ctx.clone();
         service_ctx.insert("service_name", &service.name);
@@ -541,51 +807,441 @@ impl LanguagePack for JavaPack {
             "framework",
             &service.framework.as_deref().unwrap_or("spring"),
         );
-        service_ctx.insert("java_version", &"21");
+        service_ctx.insert("dependencies", &service_dependencies(self, service)?);
+        service_ctx.insert(
+            "java_version",
+            &toolchain_version(ctx, "java").unwrap_or_else(|| "21".to_string()),
+        );
 
         // pom.xml
         let content = tera.render("java-pom.xml", &service_ctx)?;
-        std::fs::write(service_dir.join("pom.xml"), content)?;
+        out.write(service_dir.join("pom.xml"), content)?;
 
-        // Create directory structure
         let src_main = service_dir.join("src/main/java/com/example");
-        std::fs::create_dir_all(&src_main)?;
-
         // Application.java
-        let app_content = r#"package com.example;
+        let content = tera.render("java-application.java", &service_ctx)?;
+        out.write(src_main.join("Application.java"), content)?;
+
+        // application.properties
+        let content = tera.render("java-application.properties", &service_ctx)?;
+        out.write(
+            service_dir.join("src/main/resources/application.properties"),
+            content,
+        )?;
 
-import org.springframework.boot.SpringApplication;
-import org.springframework.boot.autoconfigure.SpringBootApplication;
-import org.springframework.web.bind.annotation.GetMapping;
-import org.springframework.web.bind.annotation.RestController;
+        Ok(())
+    }
+}
 
-@SpringBootApplication
-@RestController
-public class Application {
-    
-    public static void main(String[] args) {
-        SpringApplication.run(Application.class, args);
+impl LanguagePack for DotNetPack {
+    fn language(&self) -> Language {
+        Language::DotNet
     }
-    
-    @GetMapping("/healthz")
-    public String healthz() {
-        return "{\"status\":\"healthy\"}";
+
+    fn capabilities(&self) -> Vec<Capability> {
+        vec![Capability::new("aspnetcore", ["dotnet"])]
+    }
+
+    fn templates(&self) -> Vec<(&'static str, &'static str)> {
+        vec![
+            // .NET web project
+            (
+                "dotnet-service.csproj",
+                r#"<Project Sdk="Microsoft.NET.Sdk.Web">
+
+  <PropertyGroup>
+    <TargetFramework>net{{ dotnet_version | split(pat=".") | first }}.0</TargetFramework>
+    <Nullable>enable</Nullable>
+    <ImplicitUsings>enable</ImplicitUsings>
+  </PropertyGroup>
+{%- set packages = dependencies | filter(attribute="dev", value=false) %}
+{%- if packages %}
+
+  <ItemGroup>
+{%- for dep in packages %}
+    <PackageReference Include="{{ dep.name }}" Version="{{ dep.version }}" />
+{%- endfor %}
+  </ItemGroup>
+{%- endif %}
+
+</Project>
+"#,
+            ),
+            // .NET Program.cs (minimal API)
+            (
+                "dotnet-program.cs",
+                r#"var builder = WebApplication.CreateBuilder(args);
+builder.WebHost.UseUrls("http://0.0.0.0:{{ service_port }}");
+
+var app = builder.Build();
+
+app.MapGet("/healthz", () => Results.Json(new { status = "healthy" }));
+
+app.Run();
+
+// Exposes the entry point to WebApplicationFactory in the test project
+public partial class Program { }
+"#,
+            ),
+            // .NET xUnit test project
+            (
+                "dotnet-tests.csproj",
+                r#"<Project Sdk="Microsoft.NET.Sdk">
+
+  <PropertyGroup>
+    <TargetFramework>net{{ dotnet_version | split(pat=".") | first }}.0</TargetFramework>
+    <Nullable>enable</Nullable>
+    <ImplicitUsings>enable</ImplicitUsings>
+    <IsPackable>false</IsPackable>
+  </PropertyGroup>
+
+  <ItemGroup>
+{%- for dep in dependencies | filter(attribute="dev", value=true) %}
+    <PackageReference Include="{{ dep.name }}" Version="{{ dep.version }}" />
+{%- endfor %}
+  </ItemGroup>
+
+  <ItemGroup>
+    <ProjectReference Include="../src/{{ service_name }}.csproj" />
+  </ItemGroup>
+
+</Project>
+"#,
+            ),
+            // .NET /healthz test
+            (
+                "dotnet-healthz-tests.cs",
+                r#"using System.Net;
+using Microsoft.AspNetCore.Mvc.Testing;
+using Xunit;
+
+public class HealthzTests : IClassFixture<WebApplicationFactory<Program>>
+{
+    private readonly WebApplicationFactory<Program> _factory;
+
+    public HealthzTests(WebApplicationFactory<Program> factory)
+    {
+        _factory = factory;
+    }
+
+    [Fact]
+    public async Task Healthz_ReturnsHealthy()
+    {
+        var client = _factory.CreateClient();
+
+        var response = await client.GetAsync("/healthz");
+
+        Assert.Equal(HttpStatusCode.OK, response.StatusCode);
+        Assert.Contains("healthy", await response.Content.ReadAsStringAsync());
+    }
+}
+"#,
+            ),
+        ]
+    }
+
+    fn default_port(&self) -> u16 {
+        5000
+    }
+
+    fn check_commands(&self, _service: &Service) -> Vec<CheckCommand> {
+        // `dotnet build` restores from nuget.org unless told otherwise, so
+        // restore from the local package cache first and build without it
+        let packages = match std::env::var("NUGET_PACKAGES") {
+            Ok(dir) if !dir.is_empty() => dir,
+            _ => format!(
+                "{}/.nuget/packages",
+                std::env::var("HOME").unwrap_or_default()
+            ),
+        };
+        vec![
+            CheckCommand::new("dotnet", &["restore", "tests", "--source", &packages]),
+            CheckCommand::new("dotnet", &["build", "tests", "--nologo", "--no-restore"]),
+        ]
+    }
+
+    fn dependencies(&self, _service: &Service) -> Vec<Dependency> {
+        vec![
+            Dependency::new(Ecosystem::NuGet, "Microsoft.NET.Test.Sdk", "17.11.1").dev(),
+            Dependency::new(
+                Ecosystem::NuGet,
+                "Microsoft.AspNetCore.Mvc.Testing",
+                "8.0.8",
+            )
+            .dev(),
+            Dependency::new(Ecosystem::NuGet, "xunit", "2.9.0").dev(),
+            Dependency::new(Ecosystem::NuGet, "xunit.runner.visualstudio", "2.8.2").dev(),
+        ]
+    }
+
+    fn render_service(
+        &self,
+        service: &Service,
+        out: &mut OutputTree,
+        tera: &mut Tera,
+        ctx: &TeraContext,
+    ) -> Result<()> {
+        let service_dir = Path::new("services").join(&service.name);
+
+        let mut service_ctx = ctx.clone();
+        service_ctx.insert("service_name", &service.name);
+        service_ctx.insert(
+            "framework",
+            &service.framework.as_deref().unwrap_or("aspnetcore"),
+        );
+        service_ctx.insert("dependencies", &service_dependencies(self, service)?);
+        service_ctx.insert(
+            "dotnet_version",
+            &toolchain_version(ctx, "dotnet").unwrap_or_else(|| "8.0".to_string()),
+        );
+
+        // src/<name>.csproj
+        let content = tera.render("dotnet-service.csproj", &service_ctx)?;
+        out.write(
+            service_dir
+                .join("src")
+                .join(format!("{}.csproj", service.name)),
+            content,
+        )?;
+
+        // src/Program.cs
+        let content = tera.render("dotnet-program.cs", &service_ctx)?;
+        out.write(service_dir.join("src/Program.cs"), content)?;
+
+        // tests/<name>.Tests.csproj
+        let content = tera.render("dotnet-tests.csproj", &service_ctx)?;
+        out.write(
+            service_dir
+                .join("tests")
+                .join(format!("{}.Tests.csproj", service.name)),
+            content,
+        )?;
+
+        // tests/HealthzTests.cs
+        let content = tera.render("dotnet-healthz-tests.cs", &service_ctx)?;
+        out.write(service_dir.join("tests/HealthzTests.cs"), content)?;
+
+        Ok(())
     }
 }
-"#;
-        std::fs::write(src_main.join("Application.java"), app_content)?;
+
+impl LanguagePack for DenoPack {
+    fn language(&self) -> Language {
+        Language::Deno
+    }
+
+    fn capabilities(&self) -> Vec<Capability> {
+        vec![Capability::new("std", ["deno", "deno-deploy"])]
+    }
+
+    fn templates(&self) -> Vec<(&'static str, &'static str)> {
+        vec![
+            // Deno deno.json
+            (
+                "deno-config.json",
+                r#"{
+  "tasks": {
+    "dev": "deno run --watch --allow-net main.ts",
+    "start": "deno run --allow-net main.ts",
+    "test": "deno test --allow-net"{% if runtime == "deno-deploy" %},
+    "deploy": "deployctl deploy"{% endif %}
+  },
+  "imports": {
+{%- for dep in dependencies %}
+    "{{ dep.name }}": "npm:{{ dep.name }}@{{ dep.version }}"{% if not loop.last %},{% endif %}
+{%- endfor %}
+  }{% if runtime == "deno-deploy" %},
+  "deploy": {
+    "project": "{{ service_name }}",
+    "entrypoint": "main.ts"
+  }{% endif %}
+}
+"#,
+            ),
+            // Deno main.ts
+            (
+                "deno-main.ts",
+                r#"export function handler(req: Request): Response {
+  const url = new URL(req.url);
+
+  if (url.pathname === "/healthz") {
+    return Response.json({ status: "healthy" });
+  }
+
+  return new Response("Not Found", { status: 404 });
+}
+
+if (import.meta.main) {
+{%- if runtime == "deno-deploy" %}
+  // Deno Deploy assigns the listening address
+  Deno.serve(handler);
+{%- else %}
+  Deno.serve({ hostname: "0.0.0.0", port: {{ service_port }} }, handler);
+{%- endif %}
+}
+"#,
+            ),
+            // Deno main_test.ts
+            (
+                "deno-main_test.ts",
+                r#"import { handler } from "./main.ts";
+
+Deno.test("GET /healthz reports healthy", async () => {
+  const res = handler(new Request("http://localhost/healthz"));
+
+  if (res.status !== 200) {
+    throw new Error(`expected 200, got ${res.status}`);
+  }
+  const body = await res.json();
+  if (body.status !== "healthy") {
+    throw new Error(`unexpected body: ${JSON.stringify(body)}`);
+  }
+});
+"#,
+            ),
+        ]
+    }
+
+    fn default_port(&self) -> u16 {
+        8000
+    }
+
+    fn check_commands(&self, _service: &Service) -> Vec<CheckCommand> {
+        vec![CheckCommand::new(
+            "deno",
+            &["check", "main.ts", "main_test.ts"],
+        )]
+    }
+
+    fn dependencies(&self, _service: &Service) -> Vec<Dependency> {
+        Vec::new()
+    }
+
+    fn render_service(
+        &self,
+        service: &Service,
+        out: &mut OutputTree,
+        tera: &mut Tera,
+        ctx: &TeraContext,
+    ) -> Result<()> {
+        let service_dir = Path::new("services").join(&service.name);
+
+        let mut service_ctx = ctx.clone();
+        service_ctx.insert("service_name", &service.name);
+        service_ctx.insert("framework", &service.framework.as_deref().unwrap_or("std"));
+        service_ctx.insert("runtime", &service.runtime.as_deref().unwrap_or("deno"));
+        service_ctx.insert("dependencies", &service_dependencies(self, service)?);
+
+        // deno.json
+        let content = tera.render("deno-config.json", &service_ctx)?;
+        out.write(service_dir.join("deno.json"), content)?;
+
+        // main.ts
+        let content = tera.render("deno-main.ts", &service_ctx)?;
+        out.write(service_dir.join("main.ts"), content)?;
+
+        // main_test.ts
+        let content = tera.render("deno-main_test.ts", &service_ctx)?;
+        out.write(service_dir.join("main_test.ts"), content)?;
 
         Ok(())
     }
 }
 
-pub fn get_language_pack(language: &Language) -> Box<dyn LanguagePack> {
-    match language {
+pub fn get_language_pack(language: &Language) -> Result<Box<dyn LanguagePack>> {
+    Ok(match language {
         Language::Rust => Box::new(RustPack),
         Language::Node => Box::new(NodePack),
         Language::Python => Box::new(PythonPack),
         Language::Go => Box::new(GoPack),
         Language::Java => Box::new(JavaPack),
-        _ => Box::new(RustPack), // Default fallback
+        Language::DotNet => Box::new(DotNetPack),
+        Language::Deno => Box::new(DenoPack),
+        Language::Plugin(name) => Box::new(plugin::find(name).with_context(|| {
+            format!("no language pack for '{name}' (is its plugin in the --plugins directory?)")
+        })?),
+    })
+}
+
+/// Fill in the default framework and runtime of `service`, failing if the
+/// combination is not one its language pack supports.
+pub fn resolve_capability(service: &mut Service) -> Result<()> {
+    let pack = get_language_pack(&service.language)?;
+    let capabilities = pack.capabilities();
+
+    let framework = service
+        .framework
+        .as_deref()
+        .unwrap_or(&capabilities[0].framework);
+    let Some(capability) = capabilities.iter().find(|c| c.framework == framework) else {
+        let supported: Vec<_> = capabilities.iter().map(|c| c.framework.as_ref()).collect();
+        anyhow::bail!(
+            "framework '{framework}' is not supported for {} (supported: {})",
+            service.language,
+            supported.join(", ")
+        );
+    };
+
+    let runtime = service
+        .runtime
+        .as_deref()
+        .unwrap_or(&capability.runtimes[0]);
+    if !capability.runtimes.iter().any(|r| r == runtime) {
+        anyhow::bail!(
+            "runtime '{runtime}' is not supported for {} {framework} (supported: {})",
+            service.language,
+            capability.runtimes.join(", ")
+        );
+    }
+
+    service.framework = Some(framework.to_string());
+    service.runtime = Some(runtime.to_string());
+    Ok(())
+}
+
+/// Every built-in language pack, followed by the registered plugins.
+pub fn language_packs() -> Vec<Box<dyn LanguagePack>> {
+    let mut packs: Vec<Box<dyn LanguagePack>> = vec![
+        Box::new(RustPack),
+        Box::new(NodePack),
+        Box::new(PythonPack),
+        Box::new(GoPack),
+        Box::new(JavaPack),
+        Box::new(DotNetPack),
+        Box::new(DenoPack),
+    ];
+    for plugin in plugin::registered() {
+        packs.push(Box::new(plugin));
+    }
+    packs
+}
+
+/// Version configured for `language` under `toolchain` in the render context.
+///
+/// Policy pins have already been applied to the plan toolchain at this point.
+fn toolchain_version(ctx: &TeraContext, language: &str) -> Option<String> {
+    ctx.get("toolchain")?
+        .get(language)?
+        .get("version")?
+        .as_str()
+        .map(str::to_string)
+}
+
+/// Every dependency scaffolded for `service`: the pack's template
+/// dependencies, with versions overridden or extended by `Service.dependencies`.
+pub fn service_dependencies(pack: &dyn LanguagePack, service: &Service) -> Result<Vec<Dependency>> {
+    let mut deps = pack.dependencies(service);
+    let Some(ecosystem) = pack.ecosystem() else {
+        return Ok(deps);
+    };
+
+    for spec in &service.dependencies {
+        let dep = Dependency::parse(ecosystem, spec)?;
+        match deps.iter_mut().find(|d| d.same_package(&dep)) {
+            Some(existing) => existing.version = dep.version,
+            None => deps.push(dep),
+        }
     }
+
+    Ok(deps)
 }
@@ -1,6 +1,18 @@
+pub mod build_check;
 pub mod cli;
+pub mod dependency;
+pub mod error;
 pub mod git;
 pub mod language_pack;
+pub mod license;
 pub mod manifest;
+pub mod merge;
+pub mod naming;
+pub mod plugin;
 pub mod render;
+pub mod report;
+pub mod seed;
+pub mod upgrade;
 pub mod verify;
+#[cfg(all(feature = "wasm-plugins", not(target_arch = "wasm32")))]
+pub mod wasm_plugin;
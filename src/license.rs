@@ -0,0 +1,132 @@
+use anyhow::{Context, Result};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use crate::dependency::{Dependency, Ecosystem};
+
+/// License database shipped with RuneWeave.
+const BUNDLED_LICENSE_DB: &str = include_str!("../policy/licenses.yml");
+
+/// Offline mapping from packages to SPDX license expressions.
+#[derive(Debug, Default)]
+pub struct LicenseDb {
+    entries: HashMap<Ecosystem, HashMap<String, String>>,
+}
+
+impl LicenseDb {
+    pub fn bundled() -> Result<Self> {
+        Self::parse(BUNDLED_LICENSE_DB).context("Failed to parse bundled license database")
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read license database from {path:?}"))?;
+        Self::parse(&content).with_context(|| format!("Failed to parse license database {path:?}"))
+    }
+
+    pub fn parse(content: &str) -> Result<Self> {
+        let raw: BTreeMap<Ecosystem, BTreeMap<String, String>> = serde_yaml::from_str(content)?;
+
+        let mut entries: HashMap<Ecosystem, HashMap<String, String>> = HashMap::new();
+        for (ecosystem, packages) in raw {
+            for (name, expression) in packages {
+                // Reject malformed expressions up front rather than at lookup time
+                license_ids(&expression)
+                    .with_context(|| format!("Invalid SPDX expression for {ecosystem} '{name}'"))?;
+                entries
+                    .entry(ecosystem)
+                    .or_default()
+                    .insert(ecosystem.normalize_name(&name), expression);
+            }
+        }
+
+        Ok(Self { entries })
+    }
+
+    /// Add `other`'s entries, replacing any this database already has.
+    pub fn extend(&mut self, other: LicenseDb) {
+        for (ecosystem, packages) in other.entries {
+            self.entries.entry(ecosystem).or_default().extend(packages);
+        }
+    }
+
+    /// SPDX expression for `dependency`, if the database knows it.
+    pub fn license_of(&self, dependency: &Dependency) -> Option<&str> {
+        self.entries
+            .get(&dependency.ecosystem)?
+            .get(&dependency.ecosystem.normalize_name(&dependency.name))
+            .map(String::as_str)
+    }
+}
+
+/// Every license identifier referenced by an SPDX license expression,
+/// e.g. `(MIT OR Apache-2.0) AND BSD-3-Clause` or
+/// `GPL-2.0-or-later WITH Classpath-exception-2.0`.
+pub fn license_ids(expression: &str) -> Result<Vec<String>> {
+    let spaced = expression.replace('(', " ( ").replace(')', " ) ");
+    let tokens: Vec<&str> = spaced.split_whitespace().collect();
+
+    let mut ids = Vec::new();
+    let mut depth = 0usize;
+    // Alternates between expecting a license (or '(') and an operator (or ')')
+    let mut expect_license = true;
+    let mut iter = tokens.iter().peekable();
+
+    while let Some(token) = iter.next() {
+        match (*token, expect_license) {
+            ("(", true) => depth += 1,
+            (")", false) => {
+                depth = depth
+                    .checked_sub(1)
+                    .ok_or_else(|| anyhow::anyhow!("Unbalanced ')' in '{expression}'"))?;
+            }
+            (op, false) if op.eq_ignore_ascii_case("AND") || op.eq_ignore_ascii_case("OR") => {
+                expect_license = true;
+            }
+            (op, false) if op.eq_ignore_ascii_case("WITH") => {
+                // The exception identifier is not a license in its own right
+                match iter.next() {
+                    Some(exception) if is_spdx_id(exception) => {}
+                    _ => anyhow::bail!("Missing exception after WITH in '{expression}'"),
+                }
+            }
+            (id, true) if is_spdx_id(id) => {
+                ids.push(id.to_string());
+                expect_license = false;
+            }
+            (token, _) => anyhow::bail!("Unexpected '{token}' in '{expression}'"),
+        }
+    }
+
+    if ids.is_empty() || expect_license || depth != 0 {
+        anyhow::bail!("Incomplete SPDX expression '{expression}'");
+    }
+
+    Ok(ids)
+}
+
+fn is_spdx_id(token: &str) -> bool {
+    !token.is_empty()
+        && !matches!(token, "(" | ")")
+        && token
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '.' | '+' | ':'))
+}
+
+/// Whether license `id` is covered by the denied license `denied`.
+///
+/// Matching ignores case and the `-only`/`-or-later`/`+` suffixes, so denying
+/// `AGPL-3.0` also denies `AGPL-3.0-only` and `AGPL-3.0-or-later`.
+pub fn license_matches(id: &str, denied: &str) -> bool {
+    fn base(id: &str) -> String {
+        let lower = id.to_ascii_lowercase();
+        let trimmed = lower.trim_end_matches('+');
+        trimmed
+            .strip_suffix("-only")
+            .or_else(|| trimmed.strip_suffix("-or-later"))
+            .unwrap_or(trimmed)
+            .to_string()
+    }
+
+    base(id) == base(denied)
+}
@@ -1,98 +1,431 @@
-use anyhow::Result;
 use clap::Parser;
-use tracing::info;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::process::ExitCode;
+use tracing::{info, warn};
 
+mod build_check;
 mod cli;
+mod dependency;
+mod error;
 mod git;
 mod language_pack;
+mod license;
 mod manifest;
+mod merge;
+mod naming;
+mod plugin;
 mod render;
+mod report;
+mod seed;
+mod upgrade;
 mod verify;
+#[cfg(all(feature = "wasm-plugins", not(target_arch = "wasm32")))]
+mod wasm_plugin;
 
-use cli::{Cli, Commands};
-use manifest::{generate_manifest, write_manifest};
-use render::{render_templates, RenderContext};
-use verify::{verify_plan, verify_policy};
+use build_check::run_build_checks;
+use cli::{ApplyArgs, Cli, Commands, OutputFormat, UpgradeArgs};
+use error::WeaveError;
+use git::PullRequest;
+use language_pack::language_packs;
+use manifest::{
+    check_scaffold, generate_manifest, hash_content, hash_files, read_manifest, write_manifest,
+    MANIFEST_FILE,
+};
+use render::{load_templates, render_templates, template_hash, RenderContext};
+use report::{Finding, Report};
+use verify::{
+    apply_pins, plan_schema, verify_plan, verify_plan_content, verify_policy, Policy, StackPlan,
+};
 
-fn main() -> Result<()> {
+fn main() -> ExitCode {
     // Initialize tracing
-    tracing_subscriber::fmt::init();
+    // Logs go to stderr so `--format json` output on stdout stays parseable
+    tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .with_writer(std::io::stderr)
+        .init();
 
-    let cli = Cli::parse();
+    match run(Cli::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("Error: {err}");
+            ExitCode::from(err.exit_code())
+        }
+    }
+}
+
+fn run(cli: Cli) -> Result<(), WeaveError> {
+    if let Some(dir) = &cli.plugins {
+        plugin::register_plugins(dir)?;
+    }
 
     match cli.command {
-        Commands::Apply {
+        Commands::Apply(args) => {
+            let mut report = Report::default();
+            let result = if args.verify {
+                // Just verify, don't generate
+                verify_inputs(&args.plan, args.policy.as_deref(), &mut report).map(|_| {
+                    info!("Verification successful");
+                })
+            } else {
+                apply(&args, &mut report)
+            };
+            finish(args.format, report, result)
+        }
+        Commands::Verify {
             plan,
-            seed,
-            repo,
             policy,
-            out,
-            verify,
+            format,
         } => {
-            if verify {
-                // Just verify, don't generate
-                let _ = verify_plan(&plan)?;
-                let _ = verify_policy(policy.as_deref())?;
+            let mut report = Report::default();
+            let result = verify_inputs(&plan, policy.as_deref(), &mut report).map(|_| {
                 info!("Verification successful");
-                return Ok(());
+            });
+            finish(format, report, result)
+        }
+        Commands::Check { dir, format } => {
+            let drift = check_scaffold(&dir)?;
+            if format == OutputFormat::Json {
+                let json = serde_json::to_string_pretty(&drift).map_err(anyhow::Error::from)?;
+                println!("{json}");
+            }
+            if !drift.is_empty() {
+                return Err(WeaveError::Drift(drift));
             }
+            info!("Scaffold matches its manifest");
+            Ok(())
+        }
+        Commands::Upgrade(args) => {
+            let mut report = Report::default();
+            let result = upgrade(&args, &mut report);
+            finish(args.format, report, result)
+        }
+        Commands::Packs { format } => {
+            print_packs(format).map_err(WeaveError::Other)?;
+            Ok(())
+        }
+        Commands::Schema => {
+            let schema =
+                serde_json::to_string_pretty(&plan_schema()).map_err(anyhow::Error::from)?;
+            println!("{schema}");
+            Ok(())
+        }
+    }
+}
 
-            // Verify inputs
-            let stack_plan = verify_plan(&plan)?;
-            let policy_data = verify_policy(policy.as_deref())?;
+fn verify_inputs(
+    plan: &Path,
+    policy: Option<&Path>,
+    report: &mut Report,
+) -> Result<(StackPlan, Option<Policy>), WeaveError> {
+    if let Ok(plan_content) = std::fs::read_to_string(plan) {
+        report.plan_hash = Some(hash_content(&plan_content));
+    }
 
-            // Use seed or generate random
-            let seed = seed.unwrap_or_else(|| {
-                use rand::Rng;
-                rand::thread_rng().gen()
-            });
+    let stack_plan = verify_plan(plan)?;
+    let (policy_data, warnings) = verify_policy(policy, &stack_plan)?;
+    report.findings.extend(warnings.iter().map(Finding::from));
 
-            info!("Generating scaffold with seed: {}", seed);
+    Ok((stack_plan, policy_data))
+}
 
-            // Create render context
-            let ctx = RenderContext {
-                plan: stack_plan,
-                policy: policy_data,
-                seed,
-                out_dir: out.clone(),
-            };
+fn apply(args: &ApplyArgs, report: &mut Report) -> Result<(), WeaveError> {
+    let (plan, out) = (args.plan.as_path(), args.out.as_path());
 
-            // Render templates
-            render_templates(&ctx)?;
-
-            // Generate manifest
-            let plan_content = std::fs::read_to_string(&plan)?;
-            let rust_version = ctx
-                .plan
-                .toolchain
-                .rust
-                .as_ref()
-                .map(|r| r.version.clone())
-                .unwrap_or_else(|| "1.82".to_string());
-
-            let manifest = generate_manifest(
-                &plan_content,
-                seed,
-                &rust_version,
-                "1.0.0", // template version
-            )?;
-            write_manifest(&manifest, &out)?;
-
-            info!("Scaffold generated at: {:?}", out);
-
-            // Handle repository push if specified
-            if let Some(repo_spec) = repo {
-                let git_ops = git::GitOps::new(&repo_spec)?;
-                git_ops.push_to_repo(&out, "main")?;
-            }
+    // Verify inputs
+    let (mut stack_plan, policy_data) = verify_inputs(plan, args.policy.as_deref(), report)?;
 
-            Ok(())
+    // Policy pins take precedence over plan toolchain versions
+    if let Some(pin) = policy_data.as_ref().and_then(|p| p.pin.as_ref()) {
+        apply_pins(&mut stack_plan, pin);
+    }
+
+    let template_overrides = template_overrides(
+        args.templates.as_deref(),
+        policy_data.as_ref(),
+        args.policy.as_deref(),
+    )?;
+
+    // Check the repository before writing anything, and refuse to mix the
+    // scaffold into uncommitted work
+    let git_ops = args.repo.as_deref().map(git::GitOps::new).transpose()?;
+    if git_ops.is_some() && !args.force {
+        git::ensure_clean(out)?;
+    }
+
+    // Use seed or generate random
+    let seed = args.seed.unwrap_or_else(|| {
+        use rand::Rng;
+        rand::thread_rng().gen()
+    });
+
+    info!("Generating scaffold with seed: {}", seed);
+
+    // Create render context
+    let ctx = RenderContext {
+        policy: policy_data,
+        template_overrides,
+        ..RenderContext::new(stack_plan, seed, out)
+    };
+
+    // Render templates
+    let rendered = render_templates(&ctx)?;
+    report.files = rendered.files;
+
+    // Generate manifest
+    let plan_content = std::fs::read_to_string(plan)?;
+    let manifest = generate_manifest(
+        &plan_content,
+        seed,
+        &rust_version(&ctx.plan),
+        &template_hash(&ctx.template_overrides),
+        hash_files(out, &report.files)?,
+        rendered.ports,
+    )?;
+    write_manifest(&manifest, out)?;
+    report.manifest = Some(manifest);
+
+    info!("Scaffold generated at: {:?}", out);
+
+    if args.build_check {
+        let checks = run_build_checks(&ctx.plan, out, &report.files)?;
+        report.build_checks = checks.clone();
+        if checks.iter().any(|check| !check.success) {
+            return Err(WeaveError::BuildCheck(checks));
         }
-        Commands::Verify { plan, policy } => {
-            let _ = verify_plan(&plan)?;
-            let _ = verify_policy(policy.as_deref())?;
-            info!("Verification successful");
-            Ok(())
+        info!("Build check passed for {} service(s)", checks.len());
+    }
+
+    // Handle repository push if specified
+    if let Some(git_ops) = git_ops {
+        let manifest = report.manifest.as_ref().expect("manifest was just written");
+        let project = &ctx.plan.project;
+        let pr = PullRequest {
+            title: format!("Scaffold {project} with RuneWeave"),
+            body: format!(
+                "Generated by RuneWeave.\n\n\
+                 - plan hash: `{}`\n\
+                 - seed: `{seed}`\n\
+                 - template hash: `{}`\n",
+                manifest.plan_hash, manifest.template_hash
+            ),
+            head: args
+                .branch
+                .clone()
+                .unwrap_or_else(|| format!("runeweave/{project}-{seed}")),
+            base: args.base.clone(),
+        };
+        let git_policy = ctx.policy.as_ref().and_then(|p| p.git.as_ref());
+        let message = git::commit_message(
+            git_policy.and_then(|g| g.message.as_deref()),
+            project,
+            &pr.head,
+            manifest,
+        )
+        .map_err(WeaveError::InvalidPolicy)?;
+        let commit = git::ScaffoldCommit {
+            message,
+            author: git_policy.and_then(|g| g.author.clone()),
+            committer: git_policy.and_then(|g| g.committer.clone()),
+            sign: git_policy.and_then(|g| g.sign),
+            signing_key: git_policy.and_then(|g| g.signing_key.clone()),
+        };
+        let mut files = report.files.clone();
+        files.push(MANIFEST_FILE.into());
+        report.pull_request = git_ops.push_to_repo(out, &files, &commit, &pr)?;
+        if let Some(url) = &report.pull_request {
+            info!("Opened pull request: {}", url);
+        }
+    }
+
+    Ok(())
+}
+
+fn upgrade(args: &UpgradeArgs, report: &mut Report) -> Result<(), WeaveError> {
+    let dir = args.dir.as_path();
+    let manifest = read_manifest(dir)?;
+
+    // The baseline is regenerated from the plan recorded in the manifest
+    let manifest_path = dir.join(MANIFEST_FILE);
+    let stored_plan = manifest.plan.as_ref().ok_or_else(|| {
+        anyhow::anyhow!(
+            "{manifest_path:?} does not record its plan; re-run apply with this RuneWeave first"
+        )
+    })?;
+    let stored_content = serde_json::to_string_pretty(stored_plan).map_err(anyhow::Error::from)?;
+    let mut base_plan = verify_plan_content(&manifest_path, &stored_content)?;
+    let (mut new_plan, policy_data) = match &args.plan {
+        Some(plan) => verify_inputs(plan, args.policy.as_deref(), report)?,
+        None => {
+            report.plan_hash = Some(manifest.plan_hash.clone());
+            let (policy_data, warnings) = verify_policy(args.policy.as_deref(), &base_plan)?;
+            report.findings.extend(warnings.iter().map(Finding::from));
+            (base_plan.clone(), policy_data)
         }
+    };
+
+    if let Some(pin) = policy_data.as_ref().and_then(|p| p.pin.as_ref()) {
+        apply_pins(&mut base_plan, pin);
+        apply_pins(&mut new_plan, pin);
+    }
+    let template_overrides = template_overrides(
+        args.templates.as_deref(),
+        policy_data.as_ref(),
+        args.policy.as_deref(),
+    )?;
+    let templates = template_hash(&template_overrides);
+    if templates != manifest.template_hash {
+        warn!(
+            "Templates changed since the scaffold was generated; files edited on both \
+             sides whose old version cannot be regenerated will conflict"
+        );
+    }
+
+    // Render the old and the new scaffold side by side, then merge them into
+    // the user's tree
+    let work_dir = tempfile::tempdir().map_err(anyhow::Error::from)?;
+    let base_ctx = RenderContext {
+        policy: policy_data,
+        template_overrides,
+        ..RenderContext::new(base_plan, manifest.seed, work_dir.path().join("base"))
+    };
+    let base = render_templates(&base_ctx)?;
+    let new_ctx = RenderContext {
+        plan: new_plan,
+        out_dir: work_dir.path().join("new"),
+        ..base_ctx
+    };
+    let new = render_templates(&new_ctx)?;
+
+    info!("Upgrading scaffold at {:?} (seed {})", dir, manifest.seed);
+    let upgrade = upgrade::merge_scaffold(
+        dir,
+        &manifest,
+        &base_ctx.out_dir,
+        &base.files,
+        &new_ctx.out_dir,
+        &new.files,
+    )?;
+
+    // The new manifest records the new output, so `check` and the next
+    // upgrade compare against it
+    let plan_content = match &args.plan {
+        Some(plan) => std::fs::read_to_string(plan)?,
+        None => stored_content,
+    };
+    let mut new_manifest = generate_manifest(
+        &plan_content,
+        manifest.seed,
+        &rust_version(&new_ctx.plan),
+        &templates,
+        hash_files(&new_ctx.out_dir, &new.files)?,
+        new.ports,
+    )?;
+    if args.plan.is_none() {
+        // Same plan: keep the hash of the file it was first read from
+        new_manifest.plan_hash = manifest.plan_hash;
+    }
+    write_manifest(&new_manifest, dir)?;
+    report.files = new.files;
+    report.manifest = Some(new_manifest);
+    report.upgrade = Some(upgrade.clone());
+
+    if !upgrade.conflicts.is_empty() {
+        return Err(WeaveError::Conflict(upgrade));
+    }
+    info!(
+        "Upgraded scaffold: {} updated, {} added, {} removed, {} merged",
+        upgrade.updated.len(),
+        upgrade.added.len(),
+        upgrade.removed.len(),
+        upgrade.merged.len()
+    );
+    Ok(())
+}
+
+/// Templates from `templates`, else from the policy (relative to it).
+fn template_overrides(
+    templates: Option<&Path>,
+    policy: Option<&Policy>,
+    policy_path: Option<&Path>,
+) -> Result<BTreeMap<String, String>, WeaveError> {
+    let templates_dir = templates.map(Path::to_path_buf).or_else(|| {
+        let dir = policy?.templates.as_ref()?;
+        let base = policy_path?.parent().unwrap_or(Path::new("."));
+        Some(base.join(dir))
+    });
+    match &templates_dir {
+        Some(dir) => load_templates(dir).map_err(WeaveError::Render),
+        None => Ok(BTreeMap::new()),
+    }
+}
+
+/// Rust version recorded as the manifest's `toolchain`.
+fn rust_version(plan: &StackPlan) -> String {
+    plan.toolchain
+        .rust
+        .as_ref()
+        .map(|r| r.version.clone())
+        .unwrap_or_else(|| "1.82".to_string())
+}
+
+fn print_packs(format: OutputFormat) -> anyhow::Result<()> {
+    let packs = language_packs();
+
+    if format == OutputFormat::Json {
+        let packs: Vec<_> = packs
+            .iter()
+            .map(|pack| {
+                serde_json::json!({
+                    "language": pack.language(),
+                    "frameworks": pack.capabilities(),
+                })
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&packs)?);
+        return Ok(());
+    }
+
+    println!("{:<10}{:<14}RUNTIMES", "LANGUAGE", "FRAMEWORK");
+    for pack in &packs {
+        for (i, capability) in pack.capabilities().iter().enumerate() {
+            let default = if i == 0 { "*" } else { "" };
+            let runtimes: Vec<_> = capability
+                .runtimes
+                .iter()
+                .enumerate()
+                .map(|(j, runtime)| {
+                    if j == 0 {
+                        format!("{runtime}*")
+                    } else {
+                        runtime.to_string()
+                    }
+                })
+                .collect();
+            println!(
+                "{:<10}{:<14}{}",
+                pack.language().to_string(),
+                format!("{}{default}", capability.framework),
+                runtimes.join(", ")
+            );
+        }
+    }
+    println!("\n* default when the plan leaves framework or runtime unset");
+    Ok(())
+}
+
+/// Print the JSON report if requested and pass the result through, so the
+/// exit code is the same in every format.
+fn finish(
+    format: OutputFormat,
+    mut report: Report,
+    result: Result<(), WeaveError>,
+) -> Result<(), WeaveError> {
+    if format == OutputFormat::Json {
+        report.complete(&result);
+        let json = serde_json::to_string_pretty(&report).map_err(anyhow::Error::from)?;
+        println!("{json}");
     }
+    result
 }
@@ -1,44 +1,202 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
-use std::path::Path;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Component, Path, PathBuf};
 
-#[derive(Debug, Serialize, Deserialize)]
+pub const MANIFEST_FILE: &str = "weave.manifest.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WeaveManifest {
     pub template_hash: String,
     pub seed: u64,
     pub toolchain: String,
     pub plan_hash: String,
-    pub generated_at: String,
+    /// The plan the scaffold was generated from, so `upgrade` can regenerate
+    /// it. Missing from manifests written before `upgrade` existed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub plan: Option<serde_json::Value>,
+    /// Only recorded when `SOURCE_DATE_EPOCH` is set, so the same plan and
+    /// seed always produce the same manifest.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub generated_at: Option<String>,
+    /// SHA-256 of every generated file, keyed by its `/`-separated path
+    /// relative to the scaffold root.
+    #[serde(default)]
+    pub files: BTreeMap<String, String>,
+    /// Port assigned to each service, by service name.
+    #[serde(default)]
+    pub ports: BTreeMap<String, u16>,
+}
+
+/// Differences between a scaffold directory and its manifest.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+pub struct Drift {
+    pub modified: Vec<String>,
+    pub deleted: Vec<String>,
+    pub added: Vec<String>,
+}
+
+impl Drift {
+    pub fn is_empty(&self) -> bool {
+        self.modified.is_empty() && self.deleted.is_empty() && self.added.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.modified.len() + self.deleted.len() + self.added.len()
+    }
 }
 
 pub fn generate_manifest(
     plan_content: &str,
     seed: u64,
     toolchain: &str,
-    template_version: &str,
+    template_hash: &str,
+    files: BTreeMap<String, String>,
+    ports: BTreeMap<String, u16>,
 ) -> Result<WeaveManifest> {
     let plan_hash = hash_content(plan_content);
-    let template_hash = hash_content(template_version);
+    let plan = serde_json::from_str(plan_content).context("Failed to parse plan for manifest")?;
 
     Ok(WeaveManifest {
-        template_hash,
+        template_hash: template_hash.to_string(),
         seed,
         toolchain: toolchain.to_string(),
         plan_hash,
-        generated_at: chrono::Utc::now().to_rfc3339(),
+        plan: Some(plan),
+        generated_at: source_date_epoch()?,
+        files,
+        ports,
     })
 }
 
+/// Hash `files` (relative to `out_dir`) as recorded in the manifest.
+pub fn hash_files(out_dir: &Path, files: &[PathBuf]) -> Result<BTreeMap<String, String>> {
+    files
+        .iter()
+        .map(|file| {
+            let content = std::fs::read(out_dir.join(file))
+                .with_context(|| format!("Failed to read generated file {file:?}"))?;
+            Ok((manifest_path(file), hash_content(content)))
+        })
+        .collect()
+}
+
+/// `SOURCE_DATE_EPOCH` as an RFC 3339 timestamp, if set.
+///
+/// See <https://reproducible-builds.org/specs/source-date-epoch/>.
+fn source_date_epoch() -> Result<Option<String>> {
+    let Ok(value) = std::env::var("SOURCE_DATE_EPOCH") else {
+        return Ok(None);
+    };
+
+    let timestamp = value
+        .trim()
+        .parse::<i64>()
+        .ok()
+        .and_then(|secs| chrono::DateTime::from_timestamp(secs, 0))
+        .with_context(|| format!("Invalid SOURCE_DATE_EPOCH: {value:?}"))?;
+
+    Ok(Some(
+        timestamp.to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+    ))
+}
+
 pub fn write_manifest(manifest: &WeaveManifest, out_dir: &Path) -> Result<()> {
-    let manifest_path = out_dir.join("weave.manifest.json");
+    let manifest_path = out_dir.join(MANIFEST_FILE);
     let content = serde_json::to_string_pretty(manifest)?;
     std::fs::write(manifest_path, content)?;
     Ok(())
 }
 
-fn hash_content(content: &str) -> String {
+pub fn read_manifest(dir: &Path) -> Result<WeaveManifest> {
+    let manifest_path = dir.join(MANIFEST_FILE);
+    let content = std::fs::read_to_string(&manifest_path)
+        .with_context(|| format!("Failed to read {manifest_path:?}"))?;
+    serde_json::from_str(&content).with_context(|| format!("Failed to parse {manifest_path:?}"))
+}
+
+/// Compare the files under `dir` with the hashes recorded in its manifest.
+///
+/// Only files inside directories the scaffold generated into (`services/`,
+/// `.github/`, ...) are reported as added, so the rest of a clone the
+/// scaffold was committed into is not.
+pub fn check_scaffold(dir: &Path) -> Result<Drift> {
+    let manifest = read_manifest(dir)?;
+    // Manifests written before file hashes were recorded have nothing to
+    // compare against, which must not read as "no drift"
+    if manifest.files.is_empty() {
+        anyhow::bail!(
+            "{:?} does not record file hashes; re-run apply with this RuneWeave first",
+            dir.join(MANIFEST_FILE)
+        );
+    }
+
+    let mut on_disk = BTreeMap::new();
+    collect_files(dir, Path::new(""), &mut on_disk)?;
+
+    let mut drift = Drift::default();
+    for (path, hash) in &manifest.files {
+        match on_disk.remove(path) {
+            None => drift.deleted.push(path.clone()),
+            Some(full_path) => {
+                let content = std::fs::read(&full_path)
+                    .with_context(|| format!("Failed to read {full_path:?}"))?;
+                if hash_content(content) != *hash {
+                    drift.modified.push(path.clone());
+                }
+            }
+        }
+    }
+    let generated_dirs: BTreeSet<&str> = manifest
+        .files
+        .keys()
+        .flat_map(|path| path.match_indices('/').map(|(end, _)| &path[..end]))
+        .collect();
+    drift.added = on_disk
+        .into_keys()
+        .filter(|path| {
+            path.match_indices('/')
+                .any(|(end, _)| generated_dirs.contains(&path[..end]))
+        })
+        .collect();
+
+    Ok(drift)
+}
+
+fn collect_files(
+    root: &Path,
+    relative: &Path,
+    files: &mut BTreeMap<String, PathBuf>,
+) -> Result<()> {
+    let dir = root.join(relative);
+    for entry in std::fs::read_dir(&dir).with_context(|| format!("Failed to read {dir:?}"))? {
+        let entry = entry?;
+        let path = relative.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            if entry.file_name() != ".git" {
+                collect_files(root, &path, files)?;
+            }
+        } else if path != Path::new(MANIFEST_FILE) {
+            files.insert(manifest_path(&path), entry.path());
+        }
+    }
+    Ok(())
+}
+
+/// `/`-separated form of a relative path, identical on every platform.
+fn manifest_path(path: &Path) -> String {
+    path.components()
+        .filter_map(|c| match c {
+            Component::Normal(part) => Some(part.to_string_lossy()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+pub fn hash_content(content: impl AsRef<[u8]>) -> String {
     let mut hasher = Sha256::new();
-    hasher.update(content.as_bytes());
+    hasher.update(content.as_ref());
     hex::encode(hasher.finalize())
 }
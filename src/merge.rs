@@ -0,0 +1,111 @@
+//! Line-based three-way merge, in the style of `diff3 -m`.
+
+/// Result of [`merge3`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Merged {
+    pub content: String,
+    /// Number of conflict blocks left in `content`.
+    pub conflicts: usize,
+}
+
+/// Combine the changes from `base` to `ours` and from `base` to `theirs`.
+///
+/// Regions changed differently on both sides become conflict blocks marked
+/// with `labels.0` (ours) and `labels.1` (theirs), like git's.
+pub fn merge3(base: &str, ours: &str, theirs: &str, labels: (&str, &str)) -> Merged {
+    let base: Vec<_> = base.split_inclusive('\n').collect();
+    let ours: Vec<_> = ours.split_inclusive('\n').collect();
+    let theirs: Vec<_> = theirs.split_inclusive('\n').collect();
+    let in_ours = matching_lines(&base, &ours);
+    let in_theirs = matching_lines(&base, &theirs);
+
+    let mut merged = Merged {
+        content: String::new(),
+        conflicts: 0,
+    };
+    let (mut i, mut o, mut t) = (0, 0, 0);
+    loop {
+        // The next base line both sides kept ends the current chunk
+        let stable = (i..base.len()).find_map(|j| Some((j, in_ours[j]?, in_theirs[j]?)));
+        let (j, oj, tj) = stable.unwrap_or((base.len(), ours.len(), theirs.len()));
+
+        let (b, x, y) = (&base[i..j], &ours[o..oj], &theirs[t..tj]);
+        if x == b {
+            merged.content.extend(y.iter().copied());
+        } else if y == b || x == y {
+            merged.content.extend(x.iter().copied());
+        } else {
+            merged.conflicts += 1;
+            push_side(&mut merged.content, &format!("<<<<<<< {}\n", labels.0), x);
+            push_side(&mut merged.content, "=======\n", y);
+            merged.content.push_str(&format!(">>>>>>> {}\n", labels.1));
+        }
+
+        let Some((j, oj, tj)) = stable else {
+            break;
+        };
+        merged.content.push_str(base[j]);
+        (i, o, t) = (j + 1, oj + 1, tj + 1);
+    }
+    merged
+}
+
+/// Write a conflict marker followed by `lines`, ending on a line break so the
+/// next marker starts its own line.
+fn push_side(content: &mut String, marker: &str, lines: &[&str]) {
+    content.push_str(marker);
+    content.extend(lines.iter().copied());
+    if !content.ends_with('\n') {
+        content.push('\n');
+    }
+}
+
+/// For each line of `a`, the index of the line of `b` it is matched with in a
+/// longest common subsequence, if any.
+fn matching_lines(a: &[&str], b: &[&str]) -> Vec<Option<usize>> {
+    let mut matches = vec![None; a.len()];
+
+    // Scaffold files mostly differ in a few places, so only the middle needs
+    // the quadratic table
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    for (i, m) in matches.iter_mut().enumerate().take(prefix) {
+        *m = Some(i);
+    }
+    for k in 1..=suffix {
+        matches[a.len() - k] = Some(b.len() - k);
+    }
+
+    let (a_mid, b_mid) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+    let (n, m) = (a_mid.len(), b_mid.len());
+    // lengths[i * (m + 1) + j]: LCS length of a_mid[i..] and b_mid[j..]
+    let mut lengths = vec![0u32; (n + 1) * (m + 1)];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lengths[i * (m + 1) + j] = if a_mid[i] == b_mid[j] {
+                lengths[(i + 1) * (m + 1) + j + 1] + 1
+            } else {
+                lengths[(i + 1) * (m + 1) + j].max(lengths[i * (m + 1) + j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    while i < n && j < m {
+        if a_mid[i] == b_mid[j] {
+            matches[prefix + i] = Some(prefix + j);
+            i += 1;
+            j += 1;
+        } else if lengths[(i + 1) * (m + 1) + j] >= lengths[i * (m + 1) + j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    matches
+}
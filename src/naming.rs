@@ -0,0 +1,235 @@
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::verify::Language;
+
+/// Built-in naming conventions selectable from the policy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NamingConvention {
+    /// `my-service`
+    #[serde(rename = "kebab-case")]
+    KebabCase,
+    /// `my_service`
+    #[serde(rename = "snake_case")]
+    SnakeCase,
+    /// `my.service`
+    #[serde(rename = "lower-dot")]
+    LowerDot,
+}
+
+impl NamingConvention {
+    fn separator(self) -> char {
+        match self {
+            NamingConvention::KebabCase => '-',
+            NamingConvention::SnakeCase => '_',
+            NamingConvention::LowerDot => '.',
+        }
+    }
+
+    pub fn matches(self, name: &str) -> bool {
+        let separator = self.separator();
+        !name.is_empty()
+            && name.split(separator).all(|word| {
+                !word.is_empty()
+                    && word
+                        .chars()
+                        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit())
+            })
+    }
+
+    /// Rewrite `name` in this convention, splitting on separators and
+    /// camelCase boundaries.
+    pub fn convert(self, name: &str) -> String {
+        words(name).join(&self.separator().to_string())
+    }
+}
+
+impl fmt::Display for NamingConvention {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            NamingConvention::KebabCase => "kebab-case",
+            NamingConvention::SnakeCase => "snake_case",
+            NamingConvention::LowerDot => "lower-dot",
+        })
+    }
+}
+
+/// Naming rule for one entity: a built-in convention, or `{ regex: "..." }`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum NamingRule {
+    Convention(NamingConvention),
+    Regex { regex: String },
+}
+
+impl Default for NamingRule {
+    fn default() -> Self {
+        NamingRule::Convention(NamingConvention::KebabCase)
+    }
+}
+
+impl fmt::Display for NamingRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NamingRule::Convention(convention) => convention.fmt(f),
+            NamingRule::Regex { regex } => write!(f, "regex '{regex}'"),
+        }
+    }
+}
+
+/// A compiled naming rule.
+pub struct NameMatcher {
+    rule: NamingRule,
+    regex: Option<Regex>,
+}
+
+impl NameMatcher {
+    pub fn new(rule: &NamingRule) -> Result<Self> {
+        let regex = match rule {
+            NamingRule::Convention(_) => None,
+            NamingRule::Regex { regex } => {
+                // Match the whole name, not a substring of it
+                let anchored = format!("^(?:{regex})$");
+                Some(
+                    Regex::new(&anchored)
+                        .with_context(|| format!("Invalid naming regex '{regex}'"))?,
+                )
+            }
+        };
+
+        Ok(Self {
+            rule: rule.clone(),
+            regex,
+        })
+    }
+
+    pub fn matches(&self, name: &str) -> bool {
+        match (&self.rule, &self.regex) {
+            (NamingRule::Convention(convention), _) => convention.matches(name),
+            (_, Some(regex)) => regex.is_match(name),
+            _ => false,
+        }
+    }
+
+    /// A corrected name satisfying this rule and `accept`, if one can be derived.
+    pub fn suggest(&self, name: &str, accept: impl Fn(&str) -> bool) -> Option<String> {
+        let mut candidates = Vec::new();
+        if let NamingRule::Convention(convention) = &self.rule {
+            candidates.push(convention.convert(name));
+        }
+        for convention in [
+            NamingConvention::KebabCase,
+            NamingConvention::SnakeCase,
+            NamingConvention::LowerDot,
+        ] {
+            candidates.push(convention.convert(name));
+        }
+
+        candidates
+            .into_iter()
+            .find(|c| !c.is_empty() && c != name && self.matches(c) && accept(c))
+    }
+}
+
+/// Why `name` cannot be used as a service name for `language`, if it cannot.
+///
+/// Service names become Cargo package names, npm package names, Python
+/// distribution names, Go module paths and Maven artifactIds.
+pub fn language_identifier_error(language: &Language, name: &str) -> Option<&'static str> {
+    let chars_in = |allowed: fn(char) -> bool| name.chars().all(allowed);
+    let first = name.chars().next()?;
+    let last = name.chars().last()?;
+
+    match language {
+        Language::Rust => {
+            if !(first.is_ascii_alphabetic() || first == '_') {
+                Some("Cargo package names must start with a letter or '_'")
+            } else if !chars_in(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+                Some("Cargo package names may only contain letters, digits, '-' and '_'")
+            } else {
+                None
+            }
+        }
+        Language::Node | Language::Deno => {
+            if name.len() > 214 {
+                Some("npm package names must be at most 214 characters")
+            } else if first == '.' || first == '_' {
+                Some("npm package names must not start with '.' or '_'")
+            } else if !chars_in(|c| {
+                c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '-' | '.' | '_' | '~')
+            }) {
+                Some("npm package names may only contain lowercase letters, digits, '-', '.', '_' and '~'")
+            } else {
+                None
+            }
+        }
+        Language::Python => {
+            if !first.is_ascii_alphanumeric() || !last.is_ascii_alphanumeric() {
+                Some("Python package names must start and end with a letter or digit")
+            } else if !chars_in(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '.' | '_')) {
+                Some("Python package names may only contain letters, digits, '-', '.' and '_'")
+            } else {
+                None
+            }
+        }
+        Language::Go => {
+            if first == '.' || last == '.' || first == '-' {
+                Some("Go module path elements must not start or end with '.' or start with '-'")
+            } else if !chars_in(|c| {
+                c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '-' | '.' | '_' | '~')
+            }) {
+                Some("Go module paths may only contain lowercase letters, digits, '-', '.', '_' and '~'")
+            } else {
+                None
+            }
+        }
+        Language::Java => {
+            if !chars_in(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '.' | '_')) {
+                Some("Maven artifactIds may only contain letters, digits, '-', '.' and '_'")
+            } else {
+                None
+            }
+        }
+        Language::DotNet => {
+            if !first.is_ascii_alphabetic() {
+                Some(".NET project names must start with a letter")
+            } else if !chars_in(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '.' | '_')) {
+                Some(".NET project names may only contain letters, digits, '-', '.' and '_'")
+            } else {
+                None
+            }
+        }
+        Language::Plugin(_) => None,
+    }
+}
+
+/// Split a name into lowercase words on separators and camelCase boundaries.
+fn words(name: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut prev: Option<char> = None;
+
+    for c in name.chars() {
+        if !c.is_ascii_alphanumeric() {
+            if !current.is_empty() {
+                words.push(std::mem::take(&mut current));
+            }
+        } else {
+            if c.is_ascii_uppercase()
+                && prev.is_some_and(|p| p.is_ascii_lowercase() || p.is_ascii_digit())
+                && !current.is_empty()
+            {
+                words.push(std::mem::take(&mut current));
+            }
+            current.push(c.to_ascii_lowercase());
+        }
+        prev = Some(c);
+    }
+    if !current.is_empty() {
+        words.push(current);
+    }
+
+    words
+}
@@ -0,0 +1,358 @@
+//! Out-of-process language packs.
+//!
+//! A plugin is an executable in the plugins directory. RuneWeave runs it with
+//! one argument:
+//!
+//! - `describe`: print a [`PluginDescriptor`] as JSON.
+//! - `render`: read `{"service": ..., "context": ...}` (the plan service and
+//!   the Tera context the built-in packs render with) as JSON on stdin and
+//!   print `{"files": [{"path": ..., "content": ...}]}`, with paths relative to
+//!   `services/<name>`.
+//!
+//! A non-zero exit status fails the run with the plugin's stderr.
+//!
+//! `.wasm` files are WASI modules run in a sandbox instead; they speak the
+//! same protocol except that `render` writes its files below `/out`. They
+//! need the `wasm-plugins` feature; see `crate::wasm_plugin`.
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::{Mutex, MutexGuard};
+use tera::{Context as TeraContext, Tera};
+use tracing::info;
+
+use crate::build_check::CheckCommand;
+use crate::dependency::{Dependency, Ecosystem};
+use crate::language_pack::{Capability, LanguagePack};
+use crate::render::OutputTree;
+use crate::verify::{Language, Service};
+#[cfg(all(feature = "wasm-plugins", not(target_arch = "wasm32")))]
+use crate::wasm_plugin::WasmPlugin;
+
+/// Plugins registered with [`register_plugins`].
+static PLUGINS: Mutex<Vec<PluginPack>> = Mutex::new(Vec::new());
+
+fn plugins() -> MutexGuard<'static, Vec<PluginPack>> {
+    // The list is only pushed to and retained, so it stays valid even if a
+    // holder panicked
+    PLUGINS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// What a plugin prints for `describe`.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PluginDescriptor {
+    /// Value of `Service.language` the plugin renders, e.g. `elixir`.
+    pub language: String,
+    /// Same meaning as [`LanguagePack::capabilities`].
+    pub capabilities: Vec<PluginCapability>,
+    pub default_port: u16,
+    /// Registry `Service.dependencies` are checked against by the policy.
+    #[serde(default)]
+    pub ecosystem: Option<Ecosystem>,
+    /// Commands run by `apply --build-check`, each as `[program, args...]`.
+    #[serde(default)]
+    pub checks: Vec<Vec<String>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PluginCapability {
+    pub framework: String,
+    pub runtimes: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct RenderRequest<'a> {
+    service: &'a Service,
+    context: serde_json::Value,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RenderResponse {
+    files: Vec<PluginFile>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PluginFile {
+    path: PathBuf,
+    content: String,
+}
+
+/// A language pack backed by a plugin executable or WASI module.
+#[derive(Debug, Clone)]
+pub struct PluginPack {
+    path: PathBuf,
+    runner: Runner,
+    language: String,
+    capabilities: Vec<Capability>,
+    default_port: u16,
+    ecosystem: Option<Ecosystem>,
+    checks: Vec<CheckCommand>,
+}
+
+#[derive(Debug, Clone)]
+enum Runner {
+    Process,
+    #[cfg(all(feature = "wasm-plugins", not(target_arch = "wasm32")))]
+    Wasm(WasmPlugin),
+}
+
+impl PluginPack {
+    /// Run the plugin at `path` with `describe` and check its descriptor.
+    pub fn load(path: &Path) -> Result<Self> {
+        let runner = if is_wasm(path) {
+            #[cfg(all(feature = "wasm-plugins", not(target_arch = "wasm32")))]
+            {
+                Runner::Wasm(WasmPlugin::load(path)?)
+            }
+            #[cfg(not(feature = "wasm-plugins"))]
+            anyhow::bail!(
+                "WASM plugin {path:?} needs RuneWeave built with `--features wasm-plugins`"
+            );
+            #[cfg(all(feature = "wasm-plugins", target_arch = "wasm32"))]
+            anyhow::bail!("WASM plugin {path:?} cannot be loaded on this target")
+        } else {
+            Runner::Process
+        };
+        let output = match &runner {
+            Runner::Process => run(path, "describe", None)?,
+            #[cfg(all(feature = "wasm-plugins", not(target_arch = "wasm32")))]
+            Runner::Wasm(wasm) => wasm.describe()?,
+        };
+        let descriptor: PluginDescriptor = serde_json::from_slice(&output)
+            .with_context(|| format!("Plugin {path:?} printed an invalid descriptor"))?;
+
+        let language = descriptor.language;
+        if !Regex::new(Language::PLUGIN_PATTERN)?.is_match(&language) {
+            anyhow::bail!(
+                "Plugin {path:?} declares language '{language}'; \
+                 use lowercase letters, digits and '-'"
+            );
+        }
+        if Language::BUILT_IN.iter().any(|l| l.to_string() == language) {
+            anyhow::bail!("Plugin {path:?} declares built-in language '{language}'");
+        }
+        let checks = descriptor
+            .checks
+            .into_iter()
+            .map(|mut command| {
+                if command.is_empty() {
+                    anyhow::bail!("Plugin {path:?} declares an empty check command");
+                }
+                let program = command.remove(0);
+                Ok(CheckCommand {
+                    program,
+                    args: command,
+                })
+            })
+            .collect::<Result<_>>()?;
+        if descriptor.capabilities.is_empty()
+            || descriptor
+                .capabilities
+                .iter()
+                .any(|c| c.runtimes.is_empty())
+        {
+            anyhow::bail!(
+                "Plugin {path:?} must declare at least one framework, each with a runtime"
+            );
+        }
+
+        let capabilities = descriptor
+            .capabilities
+            .into_iter()
+            .map(|c| Capability::new(c.framework, c.runtimes))
+            .collect();
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            runner,
+            language,
+            capabilities,
+            default_port: descriptor.default_port,
+            ecosystem: descriptor.ecosystem,
+            checks,
+        })
+    }
+}
+
+impl LanguagePack for PluginPack {
+    fn language(&self) -> Language {
+        Language::Plugin(self.language.clone())
+    }
+
+    fn capabilities(&self) -> Vec<Capability> {
+        self.capabilities.clone()
+    }
+
+    fn render_service(
+        &self,
+        service: &Service,
+        out: &mut OutputTree,
+        _tera: &mut Tera,
+        ctx: &TeraContext,
+    ) -> Result<()> {
+        let request = serde_json::to_vec(&RenderRequest {
+            service,
+            context: ctx.clone().into_json(),
+        })?;
+        let files: Vec<(PathBuf, Vec<u8>)> = match &self.runner {
+            Runner::Process => {
+                let output = run(&self.path, "render", Some(&request))?;
+                let response: RenderResponse =
+                    serde_json::from_slice(&output).with_context(|| {
+                        format!("Plugin {:?} printed an invalid render response", self.path)
+                    })?;
+                response
+                    .files
+                    .into_iter()
+                    .map(|file| (file.path, file.content.into_bytes()))
+                    .collect()
+            }
+            #[cfg(all(feature = "wasm-plugins", not(target_arch = "wasm32")))]
+            Runner::Wasm(wasm) => wasm.render(&request)?,
+        };
+
+        let service_dir = Path::new("services").join(&service.name);
+        for (path, content) in files {
+            let relative = path.components().all(|c| matches!(c, Component::Normal(_)));
+            if !relative || path.as_os_str().is_empty() {
+                anyhow::bail!(
+                    "Plugin {:?} tried to write {path:?} outside services/{}",
+                    self.path,
+                    service.name
+                );
+            }
+            out.write(service_dir.join(&path), content)?;
+        }
+        Ok(())
+    }
+
+    fn templates(&self) -> Vec<(&'static str, &'static str)> {
+        Vec::new()
+    }
+
+    fn default_port(&self) -> u16 {
+        self.default_port
+    }
+
+    fn dependencies(&self, _service: &Service) -> Vec<Dependency> {
+        Vec::new()
+    }
+
+    fn check_commands(&self, _service: &Service) -> Vec<CheckCommand> {
+        self.checks.clone()
+    }
+
+    fn ecosystem(&self) -> Option<Ecosystem> {
+        self.ecosystem
+    }
+}
+
+/// Load every executable and `.wasm` module in `dir` as a plugin and make it
+/// available to [`crate::language_pack::get_language_pack`].
+///
+/// Loading the same directory again replaces its plugins; two plugins for one
+/// language are an error.
+pub fn register_plugins(dir: &Path) -> Result<()> {
+    let mut paths = Vec::new();
+    for entry in std::fs::read_dir(dir).with_context(|| format!("Failed to read {dir:?}"))? {
+        let entry = entry?;
+        let hidden = entry.file_name().to_string_lossy().starts_with('.');
+        let path = entry.path();
+        if !hidden
+            && entry.file_type()?.is_file()
+            && (is_wasm(&path) || is_executable(&entry.metadata()?))
+        {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+
+    let mut loaded: Vec<PluginPack> = Vec::new();
+    for path in paths {
+        let plugin = PluginPack::load(&path)?;
+        info!("Loaded plugin {:?} for {}", path, plugin.language);
+        loaded.push(plugin);
+    }
+
+    let mut plugins = plugins();
+    for plugin in loaded {
+        plugins.retain(|p| p.path != plugin.path);
+        if let Some(other) = plugins.iter().find(|p| p.language == plugin.language) {
+            anyhow::bail!(
+                "Plugins {:?} and {:?} both provide language '{}'",
+                other.path,
+                plugin.path,
+                plugin.language
+            );
+        }
+        plugins.push(plugin);
+    }
+    Ok(())
+}
+
+/// Every registered plugin.
+pub fn registered() -> Vec<PluginPack> {
+    plugins().clone()
+}
+
+/// The plugin registered for `language`, if any.
+pub fn find(language: &str) -> Option<PluginPack> {
+    plugins().iter().find(|p| p.language == language).cloned()
+}
+
+/// Run `path command`, feeding it `input` on stdin, and return its stdout.
+fn run(path: &Path, command: &str, input: Option<&[u8]>) -> Result<Vec<u8>> {
+    let mut child = Command::new(path)
+        .arg(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to run plugin {path:?}"))?;
+
+    // Write from another thread so a plugin that answers before reading all
+    // of its input cannot deadlock on a full stdout pipe.
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let input = input.unwrap_or_default().to_vec();
+    let writer = std::thread::spawn(move || stdin.write_all(&input));
+
+    let output = child
+        .wait_with_output()
+        .with_context(|| format!("Failed to run plugin {path:?}"))?;
+    // A plugin that ignores its input may close stdin early; that is fine
+    let _ = writer.join();
+
+    if !output.status.success() {
+        anyhow::bail!(
+            "Plugin {path:?} {command} failed ({}): {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(output.stdout)
+}
+
+fn is_wasm(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "wasm")
+}
+
+#[cfg(unix)]
+fn is_executable(metadata: &std::fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o111 != 0
+}
+
+#[cfg(not(unix))]
+fn is_executable(_metadata: &std::fs::Metadata) -> bool {
+    true
+}
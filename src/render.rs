@@ -1,28 +1,99 @@
 use anyhow::{Context, Result};
-use rand::rngs::StdRng;
-use rand::SeedableRng;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 use tera::{Context as TeraContext, Tera};
 
-use crate::language_pack::get_language_pack;
-use crate::verify::{Policy, StackPlan};
+use crate::error::WeaveError;
+use crate::language_pack::{get_language_pack, language_packs};
+use crate::seed::SeededValues;
+use crate::verify::{plan_schema, Policy, StackPlan};
 
 pub struct RenderContext {
     pub plan: StackPlan,
     pub policy: Option<Policy>,
     pub seed: u64,
     pub out_dir: PathBuf,
+    /// Template sources replacing built-in templates, by name; see
+    /// [`load_templates`].
+    pub template_overrides: BTreeMap<String, String>,
 }
 
-pub fn render_templates(ctx: &RenderContext) -> Result<()> {
-    let _rng = StdRng::seed_from_u64(ctx.seed);
+impl RenderContext {
+    /// Render `plan` with `seed` into `out_dir`, without a policy or template
+    /// overrides.
+    pub fn new(plan: StackPlan, seed: u64, out_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            plan,
+            policy: None,
+            seed,
+            out_dir: out_dir.into(),
+            template_overrides: BTreeMap::new(),
+        }
+    }
+}
+
+/// Writes scaffold files below an output directory, recording each path.
+pub struct OutputTree {
+    root: PathBuf,
+    files: Vec<PathBuf>,
+}
+
+impl OutputTree {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            files: Vec::new(),
+        }
+    }
+
+    /// Write `content` to `path` (relative to the output directory), creating
+    /// parent directories as needed.
+    pub fn write(&mut self, path: impl AsRef<Path>, content: impl AsRef<[u8]>) -> Result<()> {
+        let path = path.as_ref();
+        let full_path = self.root.join(path);
+        if let Some(parent) = full_path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create directory: {parent:?}"))?;
+        }
+        std::fs::write(&full_path, content)
+            .with_context(|| format!("Failed to write {full_path:?}"))?;
 
+        if !self.files.iter().any(|f| f == path) {
+            self.files.push(path.to_path_buf());
+        }
+        Ok(())
+    }
+}
+
+/// What [`render_templates`] produced.
+pub struct Rendered {
+    /// Generated files, relative to the output directory.
+    pub files: Vec<PathBuf>,
+    /// Port assigned to each service, by service name.
+    pub ports: BTreeMap<String, u16>,
+}
+
+/// Render the scaffold into `ctx.out_dir`.
+pub fn render_templates(ctx: &RenderContext) -> Result<Rendered, WeaveError> {
+    let mut out = OutputTree::new(&ctx.out_dir);
+    let ports = render_scaffold(ctx, &mut out).map_err(WeaveError::Render)?;
+    Ok(Rendered {
+        files: out.files,
+        ports,
+    })
+}
+
+fn render_scaffold(ctx: &RenderContext, out: &mut OutputTree) -> Result<BTreeMap<String, u16>> {
     // Create output directory
     std::fs::create_dir_all(&ctx.out_dir)
         .with_context(|| format!("Failed to create output directory: {:?}", ctx.out_dir))?;
 
     // Create Tera instance
     let mut tera = Tera::default();
+    let seeded = SeededValues::new(ctx.seed);
+    seeded.register(&mut tera);
+    let ports = allocate_ports(&ctx.plan, &seeded)?;
 
     // Create template context
     let mut tera_ctx = TeraContext::new();
@@ -32,39 +103,71 @@ pub fn render_templates(ctx: &RenderContext) -> Result<()> {
     tera_ctx.insert("seed", &ctx.seed);
 
     // Generate toolchain directory
-    render_toolchain_files(&ctx.out_dir, &ctx.plan)?;
+    render_toolchain_files(out, &ctx.plan)?;
+
+    // Register built-in templates, then let overrides replace them by name
+    for language_pack in language_packs() {
+        language_pack.register_templates(&mut tera)?;
+    }
+    register_ci_template(&mut tera)?;
+    tera.add_raw_templates(&ctx.template_overrides)
+        .context("Failed to load template overrides")?;
 
     // Generate services using language packs
     for service in &ctx.plan.services {
-        let language_pack = get_language_pack(&service.language);
-        language_pack.register_templates(&mut tera)?;
-        language_pack.render_service(service, &ctx.out_dir, &mut tera, &tera_ctx)?;
+        let language_pack = get_language_pack(&service.language)?;
+        let mut service_ctx = tera_ctx.clone();
+        service_ctx.insert("service_port", &ports[&service.name]);
+        language_pack.render_service(service, out, &mut tera, &service_ctx)?;
     }
 
     // Generate CI workflow
-    register_ci_template(&mut tera)?;
-    render_ci_workflow(&tera, &tera_ctx, &ctx.out_dir, &ctx.policy)?;
+    render_ci_workflow(&tera, &tera_ctx, out, &ctx.policy)?;
 
     // Copy schemas
-    copy_schemas(&ctx.out_dir)?;
+    copy_schemas(out)?;
 
-    Ok(())
+    Ok(ports)
 }
 
-fn render_toolchain_files(out_dir: &Path, plan: &StackPlan) -> Result<()> {
-    let toolchain_dir = out_dir.join("toolchain");
-    std::fs::create_dir_all(&toolchain_dir)?;
+/// Give every service a unique port: the plan's `port` if set, otherwise its
+/// language pack's default while still free, otherwise one drawn from the seed.
+fn allocate_ports(plan: &StackPlan, seeded: &SeededValues) -> Result<BTreeMap<String, u16>> {
+    let mut ports = BTreeMap::new();
+    for service in &plan.services {
+        if let Some(port) = service.port {
+            seeded.reserve_port(port);
+            ports.insert(service.name.clone(), port);
+        }
+    }
+
+    for service in plan.services.iter().filter(|s| s.port.is_none()) {
+        let default = get_language_pack(&service.language)?.default_port();
+        let port = if seeded.reserve_port(default) {
+            default
+        } else {
+            seeded.port()?
+        };
+        ports.insert(service.name.clone(), port);
+    }
+
+    Ok(ports)
+}
+
+fn render_toolchain_files(out: &mut OutputTree, plan: &StackPlan) -> Result<()> {
+    let toolchain_dir = Path::new("toolchain");
 
     // Generate rust-toolchain.toml if Rust is used
     if let Some(rust_toolchain) = &plan.toolchain.rust {
         let content = format!(
             r#"[toolchain]
-channel = "stable"
+channel = "{}"
 components = ["rustfmt", "clippy"]
 targets = [
 {}
 ]
 "#,
+            rust_toolchain.version,
             rust_toolchain
                 .targets
                 .iter()
@@ -72,17 +175,17 @@ targets = [
                 .collect::<Vec<_>>()
                 .join(",\n")
         );
-        std::fs::write(toolchain_dir.join("rust-toolchain.toml"), content)?;
+        out.write(toolchain_dir.join("rust-toolchain.toml"), content)?;
     }
 
     // Generate .node-version if Node is used
     if let Some(node_toolchain) = &plan.toolchain.node {
-        std::fs::write(toolchain_dir.join(".node-version"), &node_toolchain.version)?;
+        out.write(toolchain_dir.join(".node-version"), &node_toolchain.version)?;
     }
 
     // Generate .python-version if Python is used
     if let Some(python_toolchain) = &plan.toolchain.python {
-        std::fs::write(
+        out.write(
             toolchain_dir.join(".python-version"),
             &python_toolchain.version,
         )?;
@@ -91,22 +194,130 @@ targets = [
     // Generate go.mod if Go is used
     if let Some(go_toolchain) = &plan.toolchain.go {
         let content = format!("module {}\n\ngo {}\n", plan.project, go_toolchain.version);
-        std::fs::write(toolchain_dir.join("go.mod"), content)?;
+        out.write(toolchain_dir.join("go.mod"), content)?;
     }
 
     // Generate .java-version if Java is used
     if let Some(java_toolchain) = &plan.toolchain.java {
-        std::fs::write(toolchain_dir.join(".java-version"), &java_toolchain.version)?;
+        out.write(toolchain_dir.join(".java-version"), &java_toolchain.version)?;
+    }
+
+    // Generate .deno-version if Deno is used
+    if let Some(deno_toolchain) = &plan.toolchain.deno {
+        out.write(toolchain_dir.join(".deno-version"), &deno_toolchain.version)?;
+    }
+
+    // Generate global.json if .NET is used
+    if let Some(dotnet_toolchain) = &plan.toolchain.dotnet {
+        let content = format!(
+            r#"{{
+  "sdk": {{
+    "version": "{}",
+    "rollForward": "latestFeature"
+  }}
+}}
+"#,
+            dotnet_sdk_version(&dotnet_toolchain.version)
+        );
+        out.write(toolchain_dir.join("global.json"), content)?;
     }
 
     Ok(())
 }
 
+/// `global.json` needs a full SDK version; `8.0` becomes the first `8.0.100`
+/// feature band and rolls forward from there.
+fn dotnet_sdk_version(version: &str) -> String {
+    let mut parts = version.split('.');
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(major), Some(minor), Some(patch)) => format!("{major}.{minor}.{patch}"),
+        (Some(major), minor, None) => format!("{major}.{}.100", minor.unwrap_or("0")),
+        _ => version.to_string(),
+    }
+}
+
 fn register_ci_template(tera: &mut Tera) -> Result<()> {
-    // Multi-language CI workflow
-    tera.add_raw_template(
-        "ci.yml",
-        r#"name: ci
+    tera.add_raw_template("ci.yml", CI_TEMPLATE)?;
+    Ok(())
+}
+
+/// Load every file below `dir` as a template named by its `/`-separated
+/// relative path, e.g. `rust-cargo.toml` or `ci.yml`. Each must replace a
+/// built-in template, so a misnamed file is an error rather than ignored.
+pub fn load_templates(dir: &Path) -> Result<BTreeMap<String, String>> {
+    let mut templates = BTreeMap::new();
+    let mut dirs = vec![dir.to_path_buf()];
+    while let Some(current) = dirs.pop() {
+        let entries = std::fs::read_dir(&current)
+            .with_context(|| format!("Failed to read template directory {current:?}"))?;
+        for entry in entries {
+            let path = entry?.path();
+            if path.is_dir() {
+                dirs.push(path);
+                continue;
+            }
+
+            let name = path
+                .strip_prefix(dir)?
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            let source = std::fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read template {path:?}"))?;
+            templates.insert(name, source);
+        }
+    }
+
+    let built_in = built_in_templates();
+    let unknown: Vec<_> = templates
+        .keys()
+        .filter(|name| !built_in.contains_key(name.as_str()))
+        .map(String::as_str)
+        .collect();
+    if !unknown.is_empty() {
+        anyhow::bail!(
+            "{dir:?} has templates that match no built-in template: {}; name them \
+             after the template they replace, e.g. rust-cargo.toml or ci.yml",
+            unknown.join(", ")
+        );
+    }
+    Ok(templates)
+}
+
+/// Every built-in template by name: those of all language packs plus the CI
+/// workflow.
+fn built_in_templates() -> BTreeMap<&'static str, &'static str> {
+    let mut templates = BTreeMap::new();
+    for pack in language_packs() {
+        templates.extend(pack.templates());
+    }
+    templates.insert("ci.yml", CI_TEMPLATE);
+    templates
+}
+
+/// SHA-256 over every template a scaffold can be rendered from: the templates
+/// of all language packs plus the CI workflow, with `overrides` applied.
+pub fn template_hash(overrides: &BTreeMap<String, String>) -> String {
+    let mut sources = built_in_templates();
+    sources.extend(
+        overrides
+            .iter()
+            .map(|(name, source)| (name.as_str(), source.as_str())),
+    );
+
+    let mut hasher = Sha256::new();
+    for (name, source) in sources {
+        hasher.update(name);
+        hasher.update([0]);
+        hasher.update(source);
+        hasher.update([0]);
+    }
+    hex::encode(hasher.finalize())
+}
+
+/// Multi-language CI workflow.
+const CI_TEMPLATE: &str = r#"name: ci
 on: [push, pull_request]
 
 jobs:
@@ -155,6 +366,20 @@ jobs:
         with:
           go-version-file: 'toolchain/go.mod'
 {%- endif %}
+{%- if has_dotnet %}
+      - name: Setup .NET
+        if: matrix.service.language == 'dotnet'
+        uses: actions/setup-dotnet@v4
+        with:
+          global-json-file: 'toolchain/global.json'
+{%- endif %}
+{%- if has_deno %}
+      - name: Setup Deno
+        if: matrix.service.language == 'deno'
+        uses: denoland/setup-deno@v2
+        with:
+          deno-version-file: 'toolchain/.deno-version'
+{%- endif %}
 {%- if has_java %}
       - name: Setup Java
         if: matrix.service.language == 'java'
@@ -199,6 +424,18 @@ jobs:
         if: matrix.service.language == 'java'
         working-directory: services/${{ '{{' }} matrix.service.name {{ '}}' }}
         run: mvn test
+      
+      - name: Build and Test Deno
+        if: matrix.service.language == 'deno'
+        working-directory: services/${{ '{{' }} matrix.service.name {{ '}}' }}
+        run: |
+          deno lint
+          deno task test
+      
+      - name: Build and Test .NET
+        if: matrix.service.language == 'dotnet'
+        working-directory: services/${{ '{{' }} matrix.service.name {{ '}}' }}
+        run: dotnet test tests
 
 {%- if sbom %}
       
@@ -229,20 +466,15 @@ jobs:
 {%- if cosign %}
             sbom-${{ '{{' }} matrix.service.name {{ '}}' }}.spdx.json.sig
 {%- endif %}
-"#,
-    )?;
-
-    Ok(())
-}
+"#;
 
 fn render_ci_workflow(
     tera: &Tera,
     ctx: &TeraContext,
-    out_dir: &Path,
+    out: &mut OutputTree,
     policy: &Option<Policy>,
 ) -> Result<()> {
-    let workflows_dir = out_dir.join(".github").join("workflows");
-    std::fs::create_dir_all(&workflows_dir)?;
+    let workflows_dir = Path::new(".github").join("workflows");
 
     let mut ci_ctx = ctx.clone();
 
@@ -282,31 +514,33 @@ fn render_ci_workflow(
     let has_java = services
         .iter()
         .any(|s| s.get("language").and_then(|l| l.as_str()) == Some("java"));
+    let has_dotnet = services
+        .iter()
+        .any(|s| s.get("language").and_then(|l| l.as_str()) == Some("dotnet"));
+    let has_deno = services
+        .iter()
+        .any(|s| s.get("language").and_then(|l| l.as_str()) == Some("deno"));
 
     ci_ctx.insert("has_rust", &has_rust);
     ci_ctx.insert("has_node", &has_node);
     ci_ctx.insert("has_python", &has_python);
     ci_ctx.insert("has_go", &has_go);
     ci_ctx.insert("has_java", &has_java);
+    ci_ctx.insert("has_dotnet", &has_dotnet);
+    ci_ctx.insert("has_deno", &has_deno);
 
     let content = tera.render("ci.yml", &ci_ctx)?;
-    std::fs::write(workflows_dir.join("ci.yml"), content)?;
+    out.write(workflows_dir.join("ci.yml"), content)?;
 
     Ok(())
 }
 
-fn copy_schemas(out_dir: &Path) -> Result<()> {
-    let schemas_dir = out_dir.join("schemas");
-    std::fs::create_dir_all(&schemas_dir)?;
+fn copy_schemas(out: &mut OutputTree) -> Result<()> {
+    let schemas_dir = Path::new("schemas");
 
-    // Copy the stack.schema.json
-    if let Ok(schema_content) = std::fs::read_to_string("schemas/stack.schema.json") {
-        std::fs::write(schemas_dir.join("stack.schema.json"), schema_content)?;
-    } else {
-        // Generate from types if not found
-        let schema_content = serde_json::to_string_pretty(&schemars::schema_for!(StackPlan))?;
-        std::fs::write(schemas_dir.join("stack.schema.json"), schema_content)?;
-    }
+    // The schema is generated from the plan types so the copy never drifts
+    let schema_content = serde_json::to_string_pretty(&plan_schema())?;
+    out.write(schemas_dir.join("stack.schema.json"), schema_content + "\n")?;
 
     Ok(())
 }
@@ -0,0 +1,107 @@
+use serde::Serialize;
+use std::path::PathBuf;
+
+use crate::build_check::BuildCheck;
+use crate::error::WeaveError;
+use crate::manifest::WeaveManifest;
+use crate::upgrade::Upgrade;
+use crate::verify::{PolicyViolation, SchemaViolation, Severity};
+
+/// Machine-readable result of `verify`, `apply` or `upgrade`, printed with
+/// `--format json`.
+#[derive(Debug, Default, Serialize)]
+pub struct Report {
+    pub success: bool,
+    pub exit_code: u8,
+    /// SHA-256 of the plan file, when it could be read.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub plan_hash: Option<String>,
+    pub findings: Vec<Finding>,
+    /// Failure that is not tied to a plan location, e.g. an unreadable policy.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Generated files, relative to the output directory.
+    pub files: Vec<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub manifest: Option<WeaveManifest>,
+    /// Pull request opened for the pushed scaffold.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pull_request: Option<String>,
+    /// Per-service results of `apply --build-check`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub build_checks: Vec<BuildCheck>,
+    /// What `upgrade` did to each file it touched.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub upgrade: Option<Upgrade>,
+}
+
+/// A schema or policy problem found in the plan.
+#[derive(Debug, Clone, Serialize)]
+pub struct Finding {
+    /// `schema` or the violated policy key, e.g. `deny.npm`.
+    pub rule: String,
+    pub severity: Severity,
+    /// JSON pointer for schema findings, policy location otherwise.
+    pub location: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub column: Option<usize>,
+    pub message: String,
+}
+
+impl From<&PolicyViolation> for Finding {
+    fn from(violation: &PolicyViolation) -> Self {
+        Self {
+            rule: violation.rule.clone(),
+            severity: violation.severity,
+            location: violation.location.clone(),
+            line: None,
+            column: None,
+            message: violation.message.clone(),
+        }
+    }
+}
+
+impl From<&SchemaViolation> for Finding {
+    fn from(violation: &SchemaViolation) -> Self {
+        let location = if violation.pointer.is_empty() {
+            "/".to_string()
+        } else {
+            violation.pointer.clone()
+        };
+
+        Self {
+            rule: "schema".to_string(),
+            severity: Severity::Error,
+            location,
+            line: Some(violation.line),
+            column: Some(violation.column),
+            message: violation.message.clone(),
+        }
+    }
+}
+
+impl Report {
+    /// Record the outcome of the command, turning schema and policy failures
+    /// into findings.
+    pub fn complete(&mut self, result: &Result<(), WeaveError>) {
+        let Err(err) = result else {
+            self.success = true;
+            self.exit_code = 0;
+            return;
+        };
+
+        self.success = false;
+        self.exit_code = err.exit_code();
+        match err {
+            WeaveError::Schema { violations, .. } => {
+                self.findings.extend(violations.iter().map(Finding::from));
+            }
+            WeaveError::Policy(violations) => {
+                self.findings.extend(violations.iter().map(Finding::from));
+            }
+            other => self.error = Some(other.to_string()),
+        }
+    }
+}
@@ -0,0 +1,111 @@
+use anyhow::Result;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::{BTreeSet, HashMap};
+use std::sync::{Arc, Mutex};
+use tera::{Tera, Value};
+
+/// Ports handed out by [`SeededValues::port`].
+pub const PORT_RANGE: std::ops::RangeInclusive<u16> = 10000..=19999;
+
+/// Values derived from the `--seed`: UUIDs, ports and secret placeholders.
+///
+/// Every value is drawn from one `StdRng`, so the same seed and the same
+/// sequence of draws always produce the same scaffold.
+#[derive(Clone)]
+pub struct SeededValues {
+    state: Arc<Mutex<SeededState>>,
+}
+
+struct SeededState {
+    rng: StdRng,
+    ports: BTreeSet<u16>,
+}
+
+impl SeededValues {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(SeededState {
+                rng: StdRng::seed_from_u64(seed),
+                ports: BTreeSet::new(),
+            })),
+        }
+    }
+
+    /// A random (version 4) UUID.
+    pub fn uuid(&self) -> String {
+        let mut bytes: [u8; 16] = self.lock().rng.gen();
+        bytes[6] = (bytes[6] & 0x0f) | 0x40;
+        bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+        let hex = hex::encode(bytes);
+        format!(
+            "{}-{}-{}-{}-{}",
+            &hex[0..8],
+            &hex[8..12],
+            &hex[12..16],
+            &hex[16..20],
+            &hex[20..32]
+        )
+    }
+
+    /// A port from [`PORT_RANGE`] that has not been handed out or reserved
+    /// yet. Fails once every port in the range is taken.
+    pub fn port(&self) -> Result<u16> {
+        let mut state = self.lock();
+        if state.ports.range(PORT_RANGE).count() == PORT_RANGE.len() {
+            anyhow::bail!("every port in {PORT_RANGE:?} is taken");
+        }
+        loop {
+            let port = state.rng.gen_range(PORT_RANGE);
+            if state.ports.insert(port) {
+                return Ok(port);
+            }
+        }
+    }
+
+    /// Mark `port` as taken. Returns `false` if it already was.
+    pub fn reserve_port(&self, port: u16) -> bool {
+        self.lock().ports.insert(port)
+    }
+
+    /// A placeholder secret of `length` lowercase hex characters.
+    pub fn secret(&self, length: usize) -> String {
+        let mut state = self.lock();
+        (0..length)
+            .map(|_| char::from_digit(state.rng.gen_range(0..16), 16).unwrap())
+            .collect()
+    }
+
+    /// Expose the values to templates as `uuid()`, `port()` and
+    /// `secret(length=32)`.
+    pub fn register(&self, tera: &mut Tera) {
+        let values = self.clone();
+        tera.register_function("uuid", move |_: &HashMap<String, Value>| {
+            Ok(Value::from(values.uuid()))
+        });
+
+        let values = self.clone();
+        tera.register_function("port", move |_: &HashMap<String, Value>| {
+            values
+                .port()
+                .map(Value::from)
+                .map_err(|err| tera::Error::msg(format!("port(): {err}")))
+        });
+
+        let values = self.clone();
+        tera.register_function("secret", move |args: &HashMap<String, Value>| {
+            let length = match args.get("length") {
+                Some(length) => length
+                    .as_u64()
+                    .ok_or("secret(): `length` must be a non-negative integer")?,
+                None => 32,
+            };
+            Ok(Value::from(values.secret(length as usize)))
+        });
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, SeededState> {
+        self.state.lock().expect("seeded RNG lock poisoned")
+    }
+}
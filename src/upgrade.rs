@@ -0,0 +1,143 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::path::{Component, Path, PathBuf};
+
+use crate::error::WeaveError;
+use crate::manifest::{hash_content, hash_files, WeaveManifest};
+use crate::merge::merge3;
+
+/// Conflict marker labels for the local and the regenerated side.
+const LABELS: (&str, &str) = ("yours", "runeweave");
+
+/// What `runeweave upgrade` did to the scaffold, by `/`-separated path.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+pub struct Upgrade {
+    /// Regenerated files taken as-is because they were not edited locally.
+    pub updated: Vec<String>,
+    /// Newly generated files.
+    pub added: Vec<String>,
+    /// Files no longer generated, removed because they were not edited locally.
+    pub removed: Vec<String>,
+    /// Files changed both locally and by the generator that merged cleanly.
+    pub merged: Vec<String>,
+    /// Files changed on both sides that need resolving by hand: they now
+    /// contain conflict markers, or one side deleted them and the local
+    /// state was kept.
+    pub conflicts: Vec<String>,
+}
+
+/// Three-way merge a regenerated scaffold into `dir`.
+///
+/// `base_dir` holds the scaffold regenerated from the manifest's plan and
+/// seed, `new_dir` the new output. A baseline file is only trusted if it
+/// still hashes to what the manifest recorded (templates may have changed
+/// since); otherwise a file edited on both sides is a whole-file conflict.
+/// Paths that would reach outside `dir` are rejected before anything is
+/// written or removed.
+pub fn merge_scaffold(
+    dir: &Path,
+    manifest: &WeaveManifest,
+    base_dir: &Path,
+    base_files: &[PathBuf],
+    new_dir: &Path,
+    new_files: &[PathBuf],
+) -> Result<Upgrade, WeaveError> {
+    let base_hashes = hash_files(base_dir, base_files)?;
+    let new_paths: BTreeSet<String> = hash_files(new_dir, new_files)?.into_keys().collect();
+    let paths: BTreeSet<&String> = manifest.files.keys().chain(&new_paths).collect();
+    if let Some(path) = paths.iter().find(|path| !inside_scaffold(path)) {
+        return Err(WeaveError::Other(anyhow::anyhow!(
+            "Refusing to upgrade {path:?}: it is outside the scaffold at {dir:?}"
+        )));
+    }
+
+    let mut upgrade = Upgrade::default();
+    for path in paths {
+        let target = dir.join(path);
+        let ours = read_optional(&target)?;
+        let theirs = if new_paths.contains(path) {
+            Some(std::fs::read(new_dir.join(path))?)
+        } else {
+            None
+        };
+        // `None` when the baseline cannot be reproduced
+        let base = match manifest.files.get(path) {
+            None => Some(None),
+            Some(hash) if ours.as_ref().is_some_and(|o| hash_content(o) == *hash) => {
+                Some(ours.clone())
+            }
+            Some(hash) if base_hashes.get(path) == Some(hash) => {
+                Some(Some(std::fs::read(base_dir.join(path))?))
+            }
+            Some(_) => None,
+        };
+
+        match base {
+            // The generator did not change it
+            Some(base) if theirs == base => {}
+            // Not edited locally
+            Some(base) if ours == base => match &theirs {
+                Some(content) => {
+                    write(&target, content)?;
+                    match base {
+                        Some(_) => upgrade.updated.push(path.clone()),
+                        None => upgrade.added.push(path.clone()),
+                    }
+                }
+                None => {
+                    std::fs::remove_file(&target)
+                        .with_context(|| format!("Failed to remove {target:?}"))?;
+                    upgrade.removed.push(path.clone());
+                }
+            },
+            _ if ours == theirs => {}
+            base => {
+                let texts = (
+                    base.flatten().map(String::from_utf8).transpose(),
+                    ours.map(String::from_utf8).transpose(),
+                    theirs.map(String::from_utf8).transpose(),
+                );
+                match texts {
+                    (Ok(base), Ok(Some(ours)), Ok(Some(theirs))) => {
+                        let merged = merge3(&base.unwrap_or_default(), &ours, &theirs, LABELS);
+                        write(&target, merged.content.as_bytes())?;
+                        if merged.conflicts == 0 {
+                            upgrade.merged.push(path.clone());
+                        } else {
+                            upgrade.conflicts.push(path.clone());
+                        }
+                    }
+                    // Deleted on one side or not text: keep the local state
+                    _ => upgrade.conflicts.push(path.clone()),
+                }
+            }
+        }
+    }
+
+    Ok(upgrade)
+}
+
+/// Whether `path` only names entries below the scaffold root.
+fn inside_scaffold(path: &str) -> bool {
+    !path.is_empty()
+        && Path::new(path)
+            .components()
+            .all(|c| matches!(c, Component::Normal(_)))
+}
+
+fn read_optional(path: &Path) -> Result<Option<Vec<u8>>> {
+    match std::fs::read(path) {
+        Ok(content) => Ok(Some(content)),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err).with_context(|| format!("Failed to read {path:?}")),
+    }
+}
+
+fn write(path: &Path, content: &[u8]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create directory: {parent:?}"))?;
+    }
+    std::fs::write(path, content).with_context(|| format!("Failed to write {path:?}"))
+}
@@ -1,25 +1,40 @@
 use anyhow::{Context, Result};
+use jsonschema::{error::ValidationErrorKind, JSONSchema};
 use schemars::schema_for;
 use serde::{Deserialize, Serialize};
-use std::path::Path;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use tracing::warn;
 
-#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+use crate::dependency::{DenyRule, Dependency, Ecosystem};
+use crate::error::WeaveError;
+use crate::git::{GitIdentity, SigningFormat};
+use crate::language_pack::{get_language_pack, resolve_capability, service_dependencies};
+use crate::license::{license_ids, license_matches, LicenseDb};
+use crate::naming::{language_identifier_error, NameMatcher, NamingRule};
+
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct StackPlan {
     pub project: String,
     pub services: Vec<Service>,
     pub toolchain: ToolchainConfig,
 }
 
-#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct Service {
     pub name: String,
     pub language: Language,
     pub framework: Option<String>,
     pub runtime: Option<String>,
     pub dependencies: Vec<String>,
+    /// Port the service listens on; allocated automatically when omitted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(range(min = 1, max = 65535))]
+    pub port: Option<u16>,
 }
 
-#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
 pub enum Language {
     Rust,
@@ -30,9 +45,61 @@ pub enum Language {
     #[serde(rename = "dotnet")]
     DotNet,
     Deno,
+    /// A language provided by a plugin; see [`crate::plugin`].
+    #[serde(untagged)]
+    Plugin(String),
+}
+
+impl Language {
+    pub const BUILT_IN: [Language; 7] = [
+        Language::Rust,
+        Language::Node,
+        Language::Python,
+        Language::Go,
+        Language::Java,
+        Language::DotNet,
+        Language::Deno,
+    ];
+
+    /// Names a plugin language may use.
+    pub const PLUGIN_PATTERN: &'static str = "^[a-z][a-z0-9-]*$";
+}
+
+// Written by hand: schemars renders the untagged plugin variant as an object.
+impl schemars::JsonSchema for Language {
+    fn schema_name() -> String {
+        "Language".to_string()
+    }
+
+    fn json_schema(_: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+        let built_in: Vec<_> = Language::BUILT_IN.iter().map(|l| l.to_string()).collect();
+        serde_json::from_value(serde_json::json!({
+            "description": "A built-in language, or one provided by a language pack plugin",
+            "anyOf": [
+                { "type": "string", "enum": built_in },
+                { "type": "string", "pattern": Language::PLUGIN_PATTERN },
+            ],
+        }))
+        .expect("valid schema")
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+impl fmt::Display for Language {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Language::Rust => "rust",
+            Language::Node => "node",
+            Language::Python => "python",
+            Language::Go => "go",
+            Language::Java => "java",
+            Language::DotNet => "dotnet",
+            Language::Deno => "deno",
+            Language::Plugin(name) => name,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct ToolchainConfig {
     pub rust: Option<RustToolchain>,
     pub node: Option<NodeToolchain>,
@@ -40,39 +107,45 @@ pub struct ToolchainConfig {
     pub go: Option<GoToolchain>,
     pub java: Option<JavaToolchain>,
     pub dotnet: Option<DotNetToolchain>,
+    pub deno: Option<DenoToolchain>,
 }
 
-#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct RustToolchain {
     pub version: String,
     pub targets: Vec<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct NodeToolchain {
     pub version: String,
 }
 
-#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct PythonToolchain {
     pub version: String,
 }
 
-#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct GoToolchain {
     pub version: String,
 }
 
-#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct JavaToolchain {
     pub version: String,
 }
 
-#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct DotNetToolchain {
     pub version: String,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct DenoToolchain {
+    pub version: String,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Policy {
     pub version: u32,
@@ -80,6 +153,9 @@ pub struct Policy {
     pub pin: Option<PinPolicy>,
     pub ci: Option<CiPolicy>,
     pub naming: Option<NamingPolicy>,
+    /// Directory of template overrides, relative to the policy file.
+    pub templates: Option<PathBuf>,
+    pub git: Option<GitPolicy>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -88,6 +164,11 @@ pub struct DenyPolicy {
     pub crates: Option<Vec<String>>,
     pub npm: Option<Vec<String>>,
     pub pypi: Option<Vec<String>>,
+    /// License database extending the bundled one, relative to the policy file.
+    pub license_db: Option<PathBuf>,
+    /// Only warn about dependencies with no known license instead of failing.
+    #[serde(default)]
+    pub allow_unknown_licenses: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -131,59 +212,779 @@ pub struct CiPolicy {
     pub cosign: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct NamingPolicy {
-    pub project: String,
-    pub service: String,
+    #[serde(default)]
+    pub project: NamingRule,
+    #[serde(default)]
+    pub service: NamingRule,
+}
+
+/// How `apply --repo` makes the scaffold commit.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct GitPolicy {
+    /// Commit author; defaults to git's `user.name` and `user.email`.
+    pub author: Option<GitIdentity>,
+    /// Commit committer; defaults to the author.
+    pub committer: Option<GitIdentity>,
+    /// Tera template for the commit message, rendered with the manifest
+    /// fields plus `project` and `branch`.
+    pub message: Option<String>,
+    /// Sign the commit; defaults to git's `commit.gpgsign` and `gpg.format`.
+    pub sign: Option<SigningFormat>,
+    /// Key to sign with; defaults to git's `user.signingkey`.
+    pub signing_key: Option<String>,
 }
 
-pub fn verify_plan(plan_path: &Path) -> Result<StackPlan> {
-    let plan_content = std::fs::read_to_string(plan_path)
-        .with_context(|| format!("Failed to read plan from {plan_path:?}"))?;
+/// A single JSON Schema violation found in a plan file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaViolation {
+    /// JSON pointer to the offending value (empty for the document root).
+    pub pointer: String,
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for SchemaViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pointer = if self.pointer.is_empty() {
+            "/"
+        } else {
+            &self.pointer
+        };
+        write!(
+            f,
+            "{}:{}: {}: {}",
+            self.line, self.column, pointer, self.message
+        )
+    }
+}
+
+/// JSON Schema for `plan.json`, derived from the `StackPlan` types.
+pub fn plan_schema() -> serde_json::Value {
+    serde_json::to_value(schema_for!(StackPlan)).expect("StackPlan schema is serializable")
+}
+
+/// Validate raw plan JSON against `schema`, returning every violation found.
+pub fn validate_plan_schema(
+    plan_content: &str,
+    schema: &serde_json::Value,
+) -> Result<Vec<SchemaViolation>> {
+    let instance: serde_json::Value =
+        serde_json::from_str(plan_content).with_context(|| "Failed to parse plan.json")?;
+
+    let mut errors = Vec::new();
+    collect_schema_errors(schema, schema, &instance, "", &mut errors)?;
+    let mut violations: Vec<_> = errors
+        .into_iter()
+        .map(|(pointer, message)| {
+            let (line, column) = locate_pointer(plan_content, &pointer).unwrap_or((1, 1));
+            SchemaViolation {
+                pointer,
+                line,
+                column,
+                message,
+            }
+        })
+        .collect();
+    violations.sort_by_key(|v| (v.line, v.column));
+
+    Ok(violations)
+}
 
-    let plan: StackPlan =
-        serde_json::from_str(&plan_content).with_context(|| "Failed to parse plan.json")?;
+/// Validate `instance` (found at `prefix` in the plan) against `schema`.
+///
+/// `Option` fields are `anyOf: [T, null]` schemas, which would report a bad
+/// value inside an optional object only as the parent's `anyOf` failure, so
+/// a non-null value is validated against `T` instead to reach the leaves.
+fn collect_schema_errors(
+    root: &serde_json::Value,
+    schema: &serde_json::Value,
+    instance: &serde_json::Value,
+    prefix: &str,
+    errors: &mut Vec<(String, String)>,
+) -> Result<()> {
+    let compiled =
+        JSONSchema::compile(schema).map_err(|e| anyhow::anyhow!("Invalid stack schema: {e}"))?;
+    let Err(found) = compiled.validate(instance) else {
+        return Ok(());
+    };
+    for error in found {
+        let pointer = format!("{prefix}{}", error.instance_path);
+        let value = instance.pointer(&error.instance_path.to_string());
+        let branch = match error.kind {
+            ValidationErrorKind::AnyOf if value.is_some_and(|v| !v.is_null()) => {
+                schema_at(root, schema, &error.schema_path.to_string())
+                    .and_then(non_null_branch)
+                    .and_then(|branch| resolve_ref(root, branch))
+            }
+            _ => None,
+        };
+        match (branch, value) {
+            (Some(serde_json::Value::Object(branch)), Some(value)) => {
+                let mut branch = branch.clone();
+                if let Some(definitions) = root.get("definitions") {
+                    branch.insert("definitions".to_string(), definitions.clone());
+                }
+                collect_schema_errors(root, &branch.into(), value, &pointer, errors)?;
+            }
+            _ => errors.push((pointer, error.to_string())),
+        }
+    }
+    Ok(())
+}
+
+/// The subschema at a validation error's `schema_path`, which steps through
+/// `$ref`s without naming them.
+fn schema_at<'a>(
+    root: &'a serde_json::Value,
+    schema: &'a serde_json::Value,
+    path: &str,
+) -> Option<&'a serde_json::Value> {
+    path.split('/').skip(1).try_fold(schema, |node, segment| {
+        let segment = segment.replace("~1", "/").replace("~0", "~");
+        match resolve_ref(root, node)? {
+            serde_json::Value::Array(items) => items.get(segment.parse::<usize>().ok()?),
+            node => node.get(&segment),
+        }
+    })
+}
+
+fn resolve_ref<'a>(
+    root: &'a serde_json::Value,
+    mut node: &'a serde_json::Value,
+) -> Option<&'a serde_json::Value> {
+    while let Some(reference) = node.get("$ref").and_then(|r| r.as_str()) {
+        node = root.pointer(reference.strip_prefix('#')?)?;
+    }
+    Some(node)
+}
+
+/// The only branch of an `anyOf` list besides `{"type": "null"}`.
+fn non_null_branch(any_of: &serde_json::Value) -> Option<&serde_json::Value> {
+    let null = serde_json::json!({ "type": "null" });
+    match any_of
+        .as_array()?
+        .iter()
+        .filter(|branch| **branch != null)
+        .collect::<Vec<_>>()
+        .as_slice()
+    {
+        [branch] => Some(branch),
+        _ => None,
+    }
+}
+
+pub fn verify_plan(plan_path: &Path) -> Result<StackPlan, WeaveError> {
+    let plan_content = std::fs::read_to_string(plan_path).map_err(|e| WeaveError::Plan {
+        path: plan_path.to_path_buf(),
+        reason: anyhow::Error::new(e).context("Failed to read plan"),
+    })?;
+    verify_plan_content(plan_path, &plan_content)
+}
+
+/// [`verify_plan`] for a plan already read from `plan_path`, e.g. the one
+/// recorded in `weave.manifest.json`.
+pub fn verify_plan_content(plan_path: &Path, plan_content: &str) -> Result<StackPlan, WeaveError> {
+    let load_error = |reason| WeaveError::Plan {
+        path: plan_path.to_path_buf(),
+        reason,
+    };
 
     // Validate schema
-    let schema = schema_for!(StackPlan);
-    let _ = serde_json::to_value(&schema)?;
+    let violations = validate_plan_schema(plan_content, &plan_schema()).map_err(load_error)?;
+    if !violations.is_empty() {
+        return Err(WeaveError::Schema {
+            path: plan_path.to_path_buf(),
+            violations,
+        });
+    }
+
+    let mut plan: StackPlan = serde_json::from_str(plan_content)
+        .map_err(|e| load_error(anyhow::Error::new(e).context("Failed to parse plan.json")))?;
+    check_services_unique(&plan).map_err(load_error)?;
+    resolve_capabilities(&mut plan).map_err(load_error)?;
+    check_dependencies(&plan).map_err(load_error)?;
 
-    // Validate naming conventions
-    if !is_kebab_case(&plan.project) {
-        anyhow::bail!("Project name must be in kebab-case");
+    Ok(plan)
+}
+
+/// Resolve every service's framework and runtime against the language pack
+/// capabilities, reporting all unsupported combinations at once.
+fn resolve_capabilities(plan: &mut StackPlan) -> Result<()> {
+    let errors: Vec<_> = plan
+        .services
+        .iter_mut()
+        .filter_map(|service| {
+            resolve_capability(service)
+                .err()
+                .map(|e| format!("service '{}': {e}", service.name))
+        })
+        .collect();
+
+    if !errors.is_empty() {
+        anyhow::bail!(
+            "Unsupported language/framework/runtime combination:\n  {}\n\
+             Run `runeweave packs` to list the supported combinations",
+            errors.join("\n  ")
+        );
     }
+    Ok(())
+}
+
+/// Parse every `Service.dependencies` entry in its pack's ecosystem,
+/// reporting all malformed specs at once, so rendering and policy checks
+/// only see valid ones.
+fn check_dependencies(plan: &StackPlan) -> Result<()> {
+    let mut errors = Vec::new();
+    for service in &plan.services {
+        let Some(ecosystem) = get_language_pack(&service.language)?.ecosystem() else {
+            continue;
+        };
+        errors.extend(service.dependencies.iter().filter_map(|spec| {
+            Dependency::parse(ecosystem, spec)
+                .err()
+                .map(|e| format!("service '{}': {e}", service.name))
+        }));
+    }
+
+    if !errors.is_empty() {
+        anyhow::bail!("Invalid dependencies:\n  {}", errors.join("\n  "));
+    }
+    Ok(())
+}
 
+/// Service names and explicit ports must not be shared between services.
+fn check_services_unique(plan: &StackPlan) -> Result<()> {
+    let mut names = HashSet::new();
+    let mut ports = HashMap::new();
     for service in &plan.services {
-        if !is_kebab_case(&service.name) {
-            anyhow::bail!("Service name '{}' must be in kebab-case", service.name);
+        if !names.insert(service.name.as_str()) {
+            anyhow::bail!("Service name '{}' is used more than once", service.name);
+        }
+        if let Some(port) = service.port {
+            if let Some(other) = ports.insert(port, service.name.as_str()) {
+                anyhow::bail!(
+                    "Services '{other}' and '{}' both listen on port {port}",
+                    service.name
+                );
+            }
         }
     }
+    Ok(())
+}
 
-    Ok(plan)
+/// How a policy finding affects verification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// Fails verification (exit code 2).
+    Error,
+    /// Reported, but does not fail verification.
+    Warning,
+}
+
+/// A policy rule broken by the plan or by what would be generated from it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PolicyViolation {
+    /// Policy key that was violated, e.g. `deny.npm`.
+    pub rule: String,
+    pub severity: Severity,
+    /// Where the violation occurs, e.g. `services/api`.
+    pub location: String,
+    pub message: String,
+}
+
+impl fmt::Display for PolicyViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] {}: {}", self.rule, self.location, self.message)
+    }
 }
 
-pub fn verify_policy(policy_path: Option<&Path>) -> Result<Option<Policy>> {
-    if let Some(path) = policy_path {
-        let policy_content = std::fs::read_to_string(path)
-            .with_context(|| format!("Failed to read policy from {path:?}"))?;
+/// Load the policy and fail on any error-level violation, returning the
+/// policy together with the remaining warnings.
+pub fn verify_policy(
+    policy_path: Option<&Path>,
+    plan: &StackPlan,
+) -> Result<(Option<Policy>, Vec<PolicyViolation>), WeaveError> {
+    let (policy, violations) =
+        evaluate_policy(policy_path, plan).map_err(WeaveError::InvalidPolicy)?;
 
-        let policy: Policy =
-            serde_yaml::from_str(&policy_content).with_context(|| "Failed to parse policy YAML")?;
+    let (errors, warnings): (Vec<_>, Vec<_>) = violations
+        .into_iter()
+        .partition(|v| v.severity == Severity::Error);
+    if !errors.is_empty() {
+        return Err(WeaveError::Policy(errors));
+    }
+
+    for warning in &warnings {
+        warn!("{warning}");
+    }
+
+    Ok((policy, warnings))
+}
 
-        if policy.version != 1 {
-            anyhow::bail!("Unsupported policy version: {}", policy.version);
+/// Load the policy (if any) and collect every rule the plan violates.
+pub fn evaluate_policy(
+    policy_path: Option<&Path>,
+    plan: &StackPlan,
+) -> Result<(Option<Policy>, Vec<PolicyViolation>)> {
+    let policy = policy_path.map(load_policy).transpose()?;
+
+    // Naming conventions apply even without a policy file (kebab-case)
+    let default_naming = NamingPolicy::default();
+    let naming = policy
+        .as_ref()
+        .and_then(|p| p.naming.as_ref())
+        .unwrap_or(&default_naming);
+    let mut violations = check_naming(naming, plan)?;
+
+    if let (Some(policy), Some(path)) = (&policy, policy_path) {
+        if let Some(pin) = &policy.pin {
+            violations.extend(check_pins(pin, plan));
         }
+        if let Some(deny) = &policy.deny {
+            violations.extend(check_deny_lists(deny, plan)?);
 
-        Ok(Some(policy))
-    } else {
-        Ok(None)
+            if deny.licenses.as_ref().is_some_and(|l| !l.is_empty()) {
+                let mut db = LicenseDb::bundled()?;
+                if let Some(db_path) = &deny.license_db {
+                    let base = path.parent().unwrap_or(Path::new("."));
+                    db.extend(LicenseDb::load(&base.join(db_path))?);
+                }
+                violations.extend(check_licenses(deny, &db, plan)?);
+            }
+        }
     }
+
+    Ok((policy, violations))
 }
 
-fn is_kebab_case(s: &str) -> bool {
-    s.chars()
-        .all(|c| c.is_lowercase() || c.is_numeric() || c == '-')
-        && !s.starts_with('-')
-        && !s.ends_with('-')
-        && !s.contains("--")
+fn load_policy(path: &Path) -> Result<Policy> {
+    let policy_content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read policy from {path:?}"))?;
+
+    let policy: Policy =
+        serde_yaml::from_str(&policy_content).with_context(|| "Failed to parse policy YAML")?;
+
+    if policy.version != 1 {
+        anyhow::bail!("Unsupported policy version: {}", policy.version);
+    }
+
+    Ok(policy)
+}
+
+/// Check the project and service names against the naming policy and each
+/// service's language-specific identifier rules.
+pub fn check_naming(naming: &NamingPolicy, plan: &StackPlan) -> Result<Vec<PolicyViolation>> {
+    let project = NameMatcher::new(&naming.project)?;
+    let service = NameMatcher::new(&naming.service)?;
+
+    let mut violations = Vec::new();
+    if !project.matches(&plan.project) {
+        let mut message = format!(
+            "project name '{}' does not match {}",
+            plan.project, naming.project
+        );
+        if let Some(suggestion) = project.suggest(&plan.project, |_| true) {
+            message.push_str(&format!(" (did you mean '{suggestion}'?)"));
+        }
+        violations.push(PolicyViolation {
+            rule: "naming.project".to_string(),
+            severity: Severity::Error,
+            location: "project".to_string(),
+            message,
+        });
+    }
+
+    for svc in &plan.services {
+        let language_error = language_identifier_error(&svc.language, &svc.name);
+        if service.matches(&svc.name) && language_error.is_none() {
+            continue;
+        }
+
+        let suggestion = service.suggest(&svc.name, |candidate| {
+            language_identifier_error(&svc.language, candidate).is_none()
+        });
+        let mut message = match language_error {
+            Some(reason) if service.matches(&svc.name) => {
+                format!("service name '{}' is invalid: {reason}", svc.name)
+            }
+            _ => format!(
+                "service name '{}' does not match {}",
+                svc.name, naming.service
+            ),
+        };
+        match suggestion {
+            Some(suggestion) => message.push_str(&format!(" (did you mean '{suggestion}'?)")),
+            None => {
+                // Explain why the conventional spelling is not offered either
+                if let NamingRule::Convention(convention) = &naming.service {
+                    let converted = convention.convert(&svc.name);
+                    if let Some(reason) = language_identifier_error(&svc.language, &converted) {
+                        message
+                            .push_str(&format!(" ('{converted}' is not usable either: {reason})"));
+                    }
+                }
+            }
+        }
+        violations.push(PolicyViolation {
+            rule: "naming.service".to_string(),
+            severity: Severity::Error,
+            location: format!("services/{}", svc.name),
+            message,
+        });
+    }
+
+    Ok(violations)
+}
+
+/// Match every dependency scaffolded for each service against the
+/// `deny.crates`, `deny.npm` and `deny.pypi` lists.
+pub fn check_deny_lists(deny: &DenyPolicy, plan: &StackPlan) -> Result<Vec<PolicyViolation>> {
+    let mut rules = Vec::new();
+    for (key, ecosystem, specs) in [
+        ("deny.crates", Ecosystem::Crates, &deny.crates),
+        ("deny.npm", Ecosystem::Npm, &deny.npm),
+        ("deny.pypi", Ecosystem::PyPI, &deny.pypi),
+    ] {
+        for spec in specs.iter().flatten() {
+            rules.push((key, DenyRule::parse(ecosystem, spec)?));
+        }
+    }
+
+    let mut violations = Vec::new();
+    for service in &plan.services {
+        let pack = get_language_pack(&service.language)?;
+        for dep in service_dependencies(pack.as_ref(), service)? {
+            for (key, rule) in &rules {
+                if rule.matches(&dep) {
+                    violations.push(PolicyViolation {
+                        rule: key.to_string(),
+                        severity: Severity::Error,
+                        location: format!("services/{}", service.name),
+                        message: format!("{} '{dep}' is denied by '{}'", dep.ecosystem, rule.spec),
+                    });
+                }
+            }
+        }
+    }
+
+    Ok(violations)
+}
+
+/// Check plan toolchain versions against the policy pins.
+///
+/// A plan version is compatible with a pin when one is a segment prefix of
+/// the other, e.g. `3.12` and `3.12.6`.
+pub fn check_pins(pin: &PinPolicy, plan: &StackPlan) -> Vec<PolicyViolation> {
+    let toolchain = &plan.toolchain;
+    let pairs = [
+        (
+            "pin.rust.msrv",
+            "rust",
+            pin.rust.as_ref().map(|p| &p.msrv),
+            toolchain.rust.as_ref().map(|t| &t.version),
+        ),
+        (
+            "pin.node.version",
+            "node",
+            pin.node.as_ref().map(|p| &p.version),
+            toolchain.node.as_ref().map(|t| &t.version),
+        ),
+        (
+            "pin.python.version",
+            "python",
+            pin.python.as_ref().map(|p| &p.version),
+            toolchain.python.as_ref().map(|t| &t.version),
+        ),
+        (
+            "pin.go.version",
+            "go",
+            pin.go.as_ref().map(|p| &p.version),
+            toolchain.go.as_ref().map(|t| &t.version),
+        ),
+        (
+            "pin.java.version",
+            "java",
+            pin.java.as_ref().map(|p| &p.version),
+            toolchain.java.as_ref().map(|t| &t.version),
+        ),
+    ];
+
+    pairs
+        .into_iter()
+        .filter_map(|(rule, language, pinned, planned)| {
+            let (pinned, planned) = (pinned?, planned?);
+            (!versions_compatible(pinned, planned)).then(|| PolicyViolation {
+                rule: rule.to_string(),
+                severity: Severity::Error,
+                location: format!("toolchain/{language}"),
+                message: format!("plan requests {language} {planned} but policy pins {pinned}"),
+            })
+        })
+        .collect()
+}
+
+/// Make the policy pins authoritative for the plan toolchain.
+///
+/// Pinned versions replace compatible plan versions, and languages used by a
+/// service but missing from `toolchain` get the pinned version.
+pub fn apply_pins(plan: &mut StackPlan, pin: &PinPolicy) {
+    let uses = |language: Language| plan.services.iter().any(|s| s.language == language);
+    let (uses_rust, uses_node, uses_python, uses_go, uses_java) = (
+        uses(Language::Rust),
+        uses(Language::Node),
+        uses(Language::Python),
+        uses(Language::Go),
+        uses(Language::Java),
+    );
+    let toolchain = &mut plan.toolchain;
+
+    if let Some(rust) = &pin.rust {
+        match &mut toolchain.rust {
+            Some(t) => t.version.clone_from(&rust.msrv),
+            None if uses_rust => {
+                toolchain.rust = Some(RustToolchain {
+                    version: rust.msrv.clone(),
+                    targets: Vec::new(),
+                })
+            }
+            None => {}
+        }
+    }
+    if let Some(node) = &pin.node {
+        match &mut toolchain.node {
+            Some(t) => t.version.clone_from(&node.version),
+            None if uses_node => {
+                toolchain.node = Some(NodeToolchain {
+                    version: node.version.clone(),
+                })
+            }
+            None => {}
+        }
+    }
+    if let Some(python) = &pin.python {
+        match &mut toolchain.python {
+            Some(t) => t.version.clone_from(&python.version),
+            None if uses_python => {
+                toolchain.python = Some(PythonToolchain {
+                    version: python.version.clone(),
+                })
+            }
+            None => {}
+        }
+    }
+    if let Some(go) = &pin.go {
+        match &mut toolchain.go {
+            Some(t) => t.version.clone_from(&go.version),
+            None if uses_go => {
+                toolchain.go = Some(GoToolchain {
+                    version: go.version.clone(),
+                })
+            }
+            None => {}
+        }
+    }
+    if let Some(java) = &pin.java {
+        match &mut toolchain.java {
+            Some(t) => t.version.clone_from(&java.version),
+            None if uses_java => {
+                toolchain.java = Some(JavaToolchain {
+                    version: java.version.clone(),
+                })
+            }
+            None => {}
+        }
+    }
+}
+
+fn versions_compatible(a: &str, b: &str) -> bool {
+    let segments = |v: &str| {
+        v.trim()
+            .trim_start_matches('v')
+            .split('.')
+            .map(str::to_string)
+            .collect::<Vec<_>>()
+    };
+    let (a, b) = (segments(a), segments(b));
+    a.iter().zip(&b).all(|(x, y)| x == y)
+}
+
+/// Resolve the license of every dependency scaffolded for each service and
+/// match it against `deny.licenses`.
+pub fn check_licenses(
+    deny: &DenyPolicy,
+    db: &LicenseDb,
+    plan: &StackPlan,
+) -> Result<Vec<PolicyViolation>> {
+    let denied = deny.licenses.as_deref().unwrap_or_default();
+
+    let mut violations = Vec::new();
+    for service in &plan.services {
+        let pack = get_language_pack(&service.language)?;
+        for dep in service_dependencies(pack.as_ref(), service)? {
+            let Some(expression) = db.license_of(&dep) else {
+                // An unknown license could be a denied one
+                let severity = if deny.allow_unknown_licenses {
+                    Severity::Warning
+                } else {
+                    Severity::Error
+                };
+                violations.push(PolicyViolation {
+                    rule: "deny.licenses".to_string(),
+                    severity,
+                    location: format!("services/{}", service.name),
+                    message: format!("no license known for {} '{}'", dep.ecosystem, dep.name),
+                });
+                continue;
+            };
+
+            let ids = license_ids(expression)?;
+            if let Some(rule) = denied
+                .iter()
+                .find(|d| ids.iter().any(|id| license_matches(id, d)))
+            {
+                violations.push(PolicyViolation {
+                    rule: "deny.licenses".to_string(),
+                    severity: Severity::Error,
+                    location: format!("services/{}", service.name),
+                    message: format!(
+                        "{} '{}' is licensed under '{expression}', which includes denied license '{rule}'",
+                        dep.ecosystem, dep.name
+                    ),
+                });
+            }
+        }
+    }
+
+    Ok(violations)
+}
+
+/// Resolve a JSON pointer to the 1-based line and column of the value it
+/// refers to in `source`.
+fn locate_pointer(source: &str, pointer: &str) -> Option<(usize, usize)> {
+    let mut scanner = JsonScanner {
+        bytes: source.as_bytes(),
+        pos: 0,
+    };
+    scanner.skip_ws();
+
+    for segment in pointer.split('/').skip(1) {
+        let segment = segment.replace("~1", "/").replace("~0", "~");
+        match scanner.peek()? {
+            b'{' => {
+                scanner.pos += 1;
+                loop {
+                    scanner.skip_ws();
+                    let key = scanner.string()?;
+                    scanner.skip_ws();
+                    scanner.expect(b':')?;
+                    scanner.skip_ws();
+                    if key == segment {
+                        break;
+                    }
+                    scanner.skip_value()?;
+                    scanner.skip_ws();
+                    scanner.expect(b',')?;
+                }
+            }
+            b'[' => {
+                scanner.pos += 1;
+                let index: usize = segment.parse().ok()?;
+                for _ in 0..index {
+                    scanner.skip_ws();
+                    scanner.skip_value()?;
+                    scanner.skip_ws();
+                    scanner.expect(b',')?;
+                }
+                scanner.skip_ws();
+            }
+            _ => return None,
+        }
+    }
+
+    let prefix = &source[..scanner.pos];
+    let line = prefix.matches('\n').count() + 1;
+    let column = prefix.rsplit('\n').next().map_or(0, |l| l.chars().count()) + 1;
+    Some((line, column))
+}
+
+/// Minimal byte scanner used to map JSON pointers back to source positions.
+struct JsonScanner<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl JsonScanner<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn skip_ws(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, byte: u8) -> Option<()> {
+        (self.peek()? == byte).then(|| self.pos += 1)
+    }
+
+    fn string(&mut self) -> Option<String> {
+        let start = self.pos;
+        self.skip_string()?;
+        serde_json::from_slice(&self.bytes[start..self.pos]).ok()
+    }
+
+    fn skip_string(&mut self) -> Option<()> {
+        self.expect(b'"')?;
+        loop {
+            match self.peek()? {
+                b'\\' => self.pos += 2,
+                b'"' => {
+                    self.pos += 1;
+                    return Some(());
+                }
+                _ => self.pos += 1,
+            }
+        }
+    }
+
+    fn skip_value(&mut self) -> Option<()> {
+        match self.peek()? {
+            b'"' => self.skip_string(),
+            b'{' | b'[' => {
+                let mut depth = 0usize;
+                loop {
+                    match self.peek()? {
+                        b'"' => {
+                            self.skip_string()?;
+                            continue;
+                        }
+                        b'{' | b'[' => depth += 1,
+                        b'}' | b']' => {
+                            depth -= 1;
+                            if depth == 0 {
+                                self.pos += 1;
+                                return Some(());
+                            }
+                        }
+                        _ => {}
+                    }
+                    self.pos += 1;
+                }
+            }
+            _ => {
+                while !matches!(
+                    self.peek(),
+                    None | Some(b',' | b'}' | b']' | b' ' | b'\t' | b'\n' | b'\r')
+                ) {
+                    self.pos += 1;
+                }
+                Some(())
+            }
+        }
+    }
 }
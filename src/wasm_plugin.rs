@@ -0,0 +1,150 @@
+//! Sandboxed host for WASI language pack plugins.
+//!
+//! A `.wasm` plugin is a WASI preview 1 command module. It gets the command
+//! (`describe` or `render`) as its argument and the request on stdin, and
+//! nothing else from the host: no environment, no network and no host
+//! directories, except that `render` gets an empty scratch directory mounted
+//! at `/out`. Whatever the module writes there becomes the service's files.
+
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use wasmtime::{Config, Engine, Linker, Module, Store, StoreLimits, StoreLimitsBuilder};
+use wasmtime_wasi::pipe::{MemoryInputPipe, MemoryOutputPipe};
+use wasmtime_wasi::preview1::{self, WasiP1Ctx};
+use wasmtime_wasi::{DirPerms, FilePerms, I32Exit, WasiCtxBuilder};
+
+/// Instructions a plugin may execute per call, so a looping module fails
+/// instead of hanging the run.
+const FUEL: u64 = 10_000_000_000;
+/// Largest linear memory a plugin may grow to.
+const MEMORY_LIMIT: usize = 512 << 20;
+/// Largest stdout or stderr kept from a plugin.
+const OUTPUT_LIMIT: usize = 16 << 20;
+
+/// Guest path of the `render` output directory.
+pub const OUT_DIR: &str = "/out";
+
+#[derive(Clone)]
+pub struct WasmPlugin {
+    path: PathBuf,
+    engine: Engine,
+    module: Module,
+}
+
+struct HostState {
+    wasi: WasiP1Ctx,
+    limits: StoreLimits,
+}
+
+impl std::fmt::Debug for WasmPlugin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WasmPlugin")
+            .field("path", &self.path)
+            .finish_non_exhaustive()
+    }
+}
+
+impl WasmPlugin {
+    /// Compile the module at `path`.
+    pub fn load(path: &Path) -> Result<Self> {
+        let mut config = Config::new();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config)?;
+        let module = Module::from_file(&engine, path)
+            .with_context(|| format!("Failed to compile WASM plugin {path:?}"))?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            engine,
+            module,
+        })
+    }
+
+    /// Run `describe` and return its stdout.
+    pub fn describe(&self) -> Result<Vec<u8>> {
+        self.run("describe", &[], None)
+    }
+
+    /// Run `render` with `request` on stdin and return every file it wrote
+    /// below `/out`, by relative path.
+    pub fn render(&self, request: &[u8]) -> Result<Vec<(PathBuf, Vec<u8>)>> {
+        let out = tempfile::tempdir().context("Failed to create plugin output directory")?;
+        self.run("render", request, Some(out.path()))?;
+
+        let mut files = Vec::new();
+        let mut dirs = vec![out.path().to_path_buf()];
+        while let Some(dir) = dirs.pop() {
+            for entry in std::fs::read_dir(&dir)? {
+                let entry = entry?;
+                let file_type = entry.file_type()?;
+                let relative = entry.path().strip_prefix(out.path())?.to_path_buf();
+                if file_type.is_dir() {
+                    dirs.push(entry.path());
+                } else if file_type.is_file() {
+                    files.push((relative, std::fs::read(entry.path())?));
+                } else {
+                    anyhow::bail!(
+                        "WASM plugin {:?} wrote {relative:?}, which is not a regular file",
+                        self.path
+                    );
+                }
+            }
+        }
+        files.sort();
+        Ok(files)
+    }
+
+    fn run(&self, command: &str, stdin: &[u8], out_dir: Option<&Path>) -> Result<Vec<u8>> {
+        let stdout = MemoryOutputPipe::new(OUTPUT_LIMIT);
+        let stderr = MemoryOutputPipe::new(OUTPUT_LIMIT);
+
+        let program = self
+            .path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let mut wasi = WasiCtxBuilder::new();
+        wasi.args(&[program.as_str(), command])
+            .stdin(MemoryInputPipe::new(stdin.to_vec()))
+            .stdout(stdout.clone())
+            .stderr(stderr.clone())
+            .allow_tcp(false)
+            .allow_udp(false)
+            .allow_ip_name_lookup(false);
+        if let Some(dir) = out_dir {
+            wasi.preopened_dir(dir, OUT_DIR, DirPerms::all(), FilePerms::all())?;
+        }
+
+        let state = HostState {
+            wasi: wasi.build_p1(),
+            limits: StoreLimitsBuilder::new().memory_size(MEMORY_LIMIT).build(),
+        };
+        let mut store = Store::new(&self.engine, state);
+        store.limiter(|state| &mut state.limits);
+        store.set_fuel(FUEL)?;
+
+        let mut linker: Linker<HostState> = Linker::new(&self.engine);
+        preview1::add_to_linker_sync(&mut linker, |state| &mut state.wasi)?;
+
+        let result = linker
+            .instantiate(&mut store, &self.module)
+            .and_then(|instance| instance.get_typed_func::<(), ()>(&mut store, "_start"))
+            .and_then(|start| start.call(&mut store, ()));
+        let failure = match result {
+            Ok(()) => None,
+            Err(err) => match err.downcast_ref::<I32Exit>() {
+                Some(I32Exit(0)) => None,
+                Some(I32Exit(code)) => Some(format!("exit code {code}")),
+                None => Some(format!("{err:#}")),
+            },
+        };
+        if let Some(failure) = failure {
+            anyhow::bail!(
+                "WASM plugin {:?} {command} failed ({failure}): {}",
+                self.path,
+                String::from_utf8_lossy(&stderr.contents()).trim()
+            );
+        }
+
+        Ok(stdout.contents().to_vec())
+    }
+}
@@ -64,6 +64,393 @@ mod integration_tests {
         assert_eq!(output.status.code(), Some(0), "Expected exit code 0");
     }
 
+    fn runeweave(args: &[&str]) -> Option<i32> {
+        Command::new(env!("CARGO_BIN_EXE_runeweave"))
+            .args(args)
+            .output()
+            .expect("Failed to execute runeweave")
+            .status
+            .code()
+    }
+
+    #[test]
+    fn test_exit_code_per_failure_class() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = |name: &str| dir.path().join(name).to_string_lossy().into_owned();
+
+        // 0: valid plan and policy
+        assert_eq!(
+            runeweave(&[
+                "verify",
+                "--plan",
+                "examples/simple.json",
+                "--policy",
+                "runeweave.policy.yml",
+            ]),
+            Some(0)
+        );
+
+        // 1: plan does not match the schema
+        std::fs::write(
+            path("invalid.json"),
+            r#"{"project": "x", "services": [{}]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            runeweave(&["verify", "--plan", &path("invalid.json")]),
+            Some(1)
+        );
+
+        // 2: plan violates the policy
+        std::fs::write(
+            path("denied.json"),
+            r#"{
+                "project": "denied",
+                "services": [
+                    {"name": "api", "language": "rust", "dependencies": ["openssl-sys"]}
+                ],
+                "toolchain": {}
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            runeweave(&[
+                "verify",
+                "--plan",
+                &path("denied.json"),
+                "--policy",
+                "runeweave.policy.yml",
+            ]),
+            Some(2)
+        );
+
+        // 1: malformed --repo, rejected before anything is generated
+        assert_eq!(
+            runeweave(&[
+                "apply",
+                "--plan",
+                "examples/simple.json",
+                "--seed",
+                "42",
+                "--out",
+                &path("out"),
+                "--repo",
+                "not-a-forge:owner/repo",
+            ]),
+            Some(1)
+        );
+        assert!(!dir.path().join("out").exists());
+
+        // 3: the push fails
+        let missing = format!("file://{}", path("missing.git"));
+        assert_eq!(
+            runeweave(&[
+                "apply",
+                "--plan",
+                "examples/simple.json",
+                "--seed",
+                "42",
+                "--out",
+                &path("out"),
+                "--repo",
+                &missing,
+            ]),
+            Some(3)
+        );
+    }
+
+    fn runeweave_json(args: &[&str]) -> serde_json::Value {
+        let output = Command::new(env!("CARGO_BIN_EXE_runeweave"))
+            .args(args)
+            .args(["--format", "json"])
+            .output()
+            .expect("Failed to execute runeweave");
+        serde_json::from_slice(&output.stdout).expect("stdout is a JSON report")
+    }
+
+    #[test]
+    fn test_json_report_with_logging() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path().join("out").to_string_lossy().into_owned();
+
+        for args in [
+            vec!["verify", "--plan", "examples/simple.json"],
+            vec!["apply", "--plan", "examples/simple.json", "--out", &out],
+        ] {
+            let output = Command::new(env!("CARGO_BIN_EXE_runeweave"))
+                .args(&args)
+                .args(["--format", "json"])
+                .env("RUST_LOG", "info")
+                .output()
+                .expect("Failed to execute runeweave");
+            let report: serde_json::Value = serde_json::from_slice(&output.stdout)
+                .unwrap_or_else(|e| panic!("{args:?}: stdout is not JSON ({e})"));
+            assert_eq!(report["success"], true);
+            // The log lines still appear, on stderr
+            assert!(String::from_utf8_lossy(&output.stderr).contains("INFO"));
+        }
+    }
+
+    #[test]
+    fn test_json_report() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path().join("out").to_string_lossy().into_owned();
+
+        let report = runeweave_json(&[
+            "apply",
+            "--plan",
+            "examples/simple.json",
+            "--seed",
+            "42",
+            "--out",
+            &out,
+        ]);
+        assert_eq!(report["success"], true);
+        assert_eq!(report["exit_code"], 0);
+        assert_eq!(report["manifest"]["seed"], 42);
+        assert_eq!(report["manifest"]["plan_hash"], report["plan_hash"]);
+        let files = report["files"].as_array().unwrap();
+        assert!(files.contains(&"services/api/Cargo.toml".into()));
+
+        let plan = dir.path().join("denied.json");
+        std::fs::write(
+            &plan,
+            r#"{
+                "project": "denied",
+                "services": [
+                    {"name": "api", "language": "rust", "dependencies": ["openssl-sys"]}
+                ],
+                "toolchain": {}
+            }"#,
+        )
+        .unwrap();
+        let report = runeweave_json(&[
+            "verify",
+            "--plan",
+            &plan.to_string_lossy(),
+            "--policy",
+            "runeweave.policy.yml",
+        ]);
+        assert_eq!(report["success"], false);
+        assert_eq!(report["exit_code"], 2);
+        assert_eq!(
+            report["findings"],
+            serde_json::json!([{
+                "rule": "deny.crates",
+                "severity": "error",
+                "location": "services/api",
+                "message": "crate 'openssl-sys@*' is denied by 'openssl-sys'",
+            }])
+        );
+    }
+
+    #[test]
+    fn test_apply_pushes_to_plain_git_remote() {
+        let dir = tempfile::tempdir().unwrap();
+        let remote = dir.path().join("remote.git");
+        let remote_repo = git2::Repository::init_bare(&remote).unwrap();
+        let out = dir.path().join("out").to_string_lossy().into_owned();
+
+        let report = runeweave_json(&[
+            "apply",
+            "--plan",
+            "examples/simple.json",
+            "--seed",
+            "42",
+            "--out",
+            &out,
+            "--repo",
+            &format!("file://{}", remote.display()),
+            "--branch",
+            "scaffold",
+        ]);
+        assert_eq!(report["success"], true, "{report}");
+        assert!(report.get("pull_request").is_none());
+
+        // The remote was empty, so the scaffold became its base branch
+        let base = remote_repo
+            .find_reference("refs/heads/main")
+            .unwrap()
+            .peel_to_commit()
+            .unwrap();
+        assert!(base
+            .tree()
+            .unwrap()
+            .get_path(std::path::Path::new("services/api/Cargo.toml"))
+            .is_ok());
+        assert!(remote_repo.find_reference("refs/heads/scaffold").is_err());
+
+        // Later scaffolds branch off it
+        let out = dir.path().join("out-2").to_string_lossy().into_owned();
+        let report = runeweave_json(&[
+            "apply",
+            "--plan",
+            "examples/simple.json",
+            "--seed",
+            "43",
+            "--out",
+            &out,
+            "--repo",
+            &format!("file://{}", remote.display()),
+            "--branch",
+            "scaffold",
+        ]);
+        assert_eq!(report["success"], true, "{report}");
+        // A plain remote has no forge API to open a pull request with
+        assert!(report.get("pull_request").is_none());
+
+        let commit = remote_repo
+            .find_reference("refs/heads/scaffold")
+            .unwrap()
+            .peel_to_commit()
+            .unwrap();
+        assert_eq!(commit.parent_id(0).unwrap(), base.id());
+    }
+
+    #[test]
+    fn test_source_date_epoch_sets_manifest_timestamp() {
+        let dir = tempfile::tempdir().unwrap();
+        let apply = |out: &str, epoch: &str| {
+            Command::new(env!("CARGO_BIN_EXE_runeweave"))
+                .args(["apply", "--plan", "examples/simple.json", "--seed", "42"])
+                .args(["--out", out])
+                .env("SOURCE_DATE_EPOCH", epoch)
+                .output()
+                .expect("Failed to execute runeweave")
+        };
+        let manifest = |out: &str| {
+            std::fs::read_to_string(std::path::Path::new(out).join("weave.manifest.json")).unwrap()
+        };
+
+        let first = dir.path().join("first").to_string_lossy().into_owned();
+        let second = dir.path().join("second").to_string_lossy().into_owned();
+        assert!(apply(&first, "1700000000").status.success());
+        assert!(apply(&second, "1700000000").status.success());
+        assert_eq!(manifest(&first), manifest(&second));
+        let parsed: serde_json::Value = serde_json::from_str(&manifest(&first)).unwrap();
+        assert_eq!(parsed["generated_at"], "2023-11-14T22:13:20Z");
+
+        let invalid = dir.path().join("invalid").to_string_lossy().into_owned();
+        let output = apply(&invalid, "yesterday");
+        assert_eq!(output.status.code(), Some(1));
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(
+            stderr.contains("Invalid SOURCE_DATE_EPOCH: \"yesterday\""),
+            "{stderr}"
+        );
+    }
+
+    #[test]
+    fn test_upgrade_merges_local_edits() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path().join("out");
+        let out_arg = out.to_string_lossy().into_owned();
+        assert_eq!(
+            runeweave(&[
+                "apply",
+                "--plan",
+                "examples/simple.json",
+                "--seed",
+                "42",
+                "--out",
+                &out_arg,
+            ]),
+            Some(0)
+        );
+
+        // Without a new plan nothing changes
+        let report = runeweave_json(&["upgrade", "--dir", &out_arg]);
+        assert_eq!(report["success"], true, "{report}");
+        assert_eq!(
+            report["upgrade"],
+            serde_json::json!({
+                "updated": [], "added": [], "removed": [], "merged": [], "conflicts": []
+            })
+        );
+
+        // Edit the CI workflow and add a service to the plan
+        let ci = out.join(".github/workflows/ci.yml");
+        let workflow = std::fs::read_to_string(&ci).unwrap();
+        std::fs::write(&ci, workflow.replacen("name: ci\n", "name: ci-custom\n", 1)).unwrap();
+        let mut plan: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string("examples/simple.json").unwrap())
+                .unwrap();
+        plan["services"]
+            .as_array_mut()
+            .unwrap()
+            .push(serde_json::json!({"name": "worker", "language": "rust", "dependencies": []}));
+        let plan_path = dir.path().join("plan.json");
+        std::fs::write(&plan_path, plan.to_string()).unwrap();
+        let plan_arg = plan_path.to_string_lossy().into_owned();
+
+        let report = runeweave_json(&["upgrade", "--dir", &out_arg, "--plan", &plan_arg]);
+        assert_eq!(report["success"], true, "{report}");
+        assert_eq!(
+            report["upgrade"]["merged"],
+            serde_json::json!([".github/workflows/ci.yml"])
+        );
+        assert!(report["upgrade"]["added"]
+            .as_array()
+            .unwrap()
+            .contains(&"services/worker/Cargo.toml".into()));
+        let workflow = std::fs::read_to_string(&ci).unwrap();
+        assert!(workflow.starts_with("name: ci-custom\n"));
+        assert!(workflow.contains("- name: worker"));
+
+        // Adding a matrix entry where the generator adds one leaves a conflict
+        let edited = workflow.replace(
+            "    steps:",
+            "          - name: jobs\n            language: go\n    steps:",
+        );
+        std::fs::write(&ci, edited).unwrap();
+        plan["services"]
+            .as_array_mut()
+            .unwrap()
+            .push(serde_json::json!({"name": "mailer", "language": "rust", "dependencies": []}));
+        std::fs::write(&plan_path, plan.to_string()).unwrap();
+        let report = runeweave_json(&["upgrade", "--dir", &out_arg, "--plan", &plan_arg]);
+        assert_eq!(report["success"], false);
+        assert_eq!(report["exit_code"], 1);
+        assert_eq!(
+            report["upgrade"]["conflicts"],
+            serde_json::json!([".github/workflows/ci.yml"])
+        );
+        let workflow = std::fs::read_to_string(&ci).unwrap();
+        assert!(workflow.contains("<<<<<<< yours\n"));
+        assert!(workflow.contains(">>>>>>> runeweave\n"));
+        assert!(out.join("services/mailer/Cargo.toml").exists());
+    }
+
+    #[test]
+    fn test_upgrade_rejects_paths_outside_scaffold() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path().join("out");
+        let out_arg = out.to_string_lossy().into_owned();
+        assert_eq!(
+            runeweave(&["apply", "--plan", "examples/simple.json", "--out", &out_arg,]),
+            Some(0)
+        );
+
+        // A tampered manifest claims a file next to the scaffold, with the
+        // hash it has on disk, so the upgrade would otherwise delete it
+        let victim = dir.path().join("victim");
+        std::fs::write(&victim, "keep me").unwrap();
+        let manifest_path = out.join("weave.manifest.json");
+        let mut manifest: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&manifest_path).unwrap()).unwrap();
+        manifest["files"]["../victim"] = runeweave::manifest::hash_content("keep me").into();
+        std::fs::write(&manifest_path, manifest.to_string()).unwrap();
+
+        let report = runeweave_json(&["upgrade", "--dir", &out_arg]);
+        assert_eq!(report["success"], false);
+        assert_eq!(report["exit_code"], 1);
+        assert!(report["error"]
+            .as_str()
+            .unwrap()
+            .contains("Refusing to upgrade \"../victim\""));
+        assert_eq!(std::fs::read_to_string(&victim).unwrap(), "keep me");
+    }
+
     #[test]
     fn test_nats_exactly_once() {
         // Skip if running in musl environment
@@ -3,6 +3,7 @@ mod tests {
     use std::fs;
     use std::io::Write;
     use std::path::PathBuf;
+    use std::process::Command;
     use tempfile::TempDir;
 
     // Helper to create a test plan file
@@ -45,6 +46,23 @@ mod tests {
         (dir, plan_path)
     }
 
+    // Helper to write `content` as the plan file in `dir`
+    fn write_plan(dir: &std::path::Path, content: &str) -> PathBuf {
+        let plan_path = dir.join("plan.json");
+        fs::write(&plan_path, content).unwrap();
+        plan_path
+    }
+
+    // Helper to render the plan at `plan_path` with `seed` into `out`
+    fn render_context(
+        plan_path: &std::path::Path,
+        seed: u64,
+        out: &std::path::Path,
+    ) -> runeweave::render::RenderContext {
+        let plan = runeweave::verify::verify_plan(plan_path).unwrap();
+        runeweave::render::RenderContext::new(plan, seed, out)
+    }
+
     #[test]
     fn test_verify_valid_plan() {
         let (_dir, plan_path) = create_test_plan();
@@ -52,9 +70,357 @@ mod tests {
         assert!(plan.is_ok());
     }
 
+    #[test]
+    fn test_schema_violations_report_every_location() {
+        let plan_content = r#"{
+  "project": "demo",
+  "services": [
+    {
+      "name": "api",
+      "language": "COBOL",
+      "dependencies": []
+    },
+    {
+      "name": "web",
+      "language": "node",
+      "dependencies": "none"
+    }
+  ]
+}"#;
+
+        let schema = runeweave::verify::plan_schema();
+        let violations = runeweave::verify::validate_plan_schema(plan_content, &schema).unwrap();
+
+        let located: Vec<_> = violations
+            .iter()
+            .map(|v| (v.pointer.as_str(), v.line, v.column))
+            .collect();
+        assert_eq!(
+            located,
+            vec![
+                ("", 1, 1),
+                ("/services/0/language", 6, 19),
+                ("/services/1/dependencies", 12, 23),
+            ]
+        );
+        assert!(violations[0].message.contains("toolchain"));
+    }
+
+    #[test]
+    fn test_schema_violations_inside_optional_objects() {
+        let plan_content = r#"{
+  "project": "demo",
+  "services": [],
+  "toolchain": {
+    "rust": {
+      "version": 1,
+      "targets": []
+    },
+    "node": null
+  }
+}"#;
+
+        let schema = runeweave::verify::plan_schema();
+        let violations = runeweave::verify::validate_plan_schema(plan_content, &schema).unwrap();
+
+        let located: Vec<_> = violations
+            .iter()
+            .map(|v| (v.pointer.as_str(), v.line, v.column))
+            .collect();
+        assert_eq!(located, vec![("/toolchain/rust/version", 6, 18)]);
+        assert!(
+            violations[0].message.contains("is not of type \"string\""),
+            "{}",
+            violations[0].message
+        );
+    }
+
+    #[test]
+    fn test_verify_rejects_schema_mismatch() {
+        let dir = TempDir::new().unwrap();
+        let plan_path = write_plan(
+            dir.path(),
+            r#"{"project": "demo", "services": [{"name": "api", "type": "api"}], "toolchain": {}}"#,
+        );
+
+        let err = runeweave::verify::verify_plan(&plan_path).unwrap_err();
+        let message = err.to_string();
+        assert!(message.contains("/services/0"), "{message}");
+        assert!(
+            message.contains("\"language\" is a required property"),
+            "{message}"
+        );
+    }
+
+    #[test]
+    fn test_checked_in_schema_matches_types() {
+        let checked_in: serde_json::Value =
+            serde_json::from_str(include_str!("../schemas/stack.schema.json")).unwrap();
+        assert_eq!(
+            checked_in,
+            runeweave::verify::plan_schema(),
+            "schemas/stack.schema.json has drifted from StackPlan; \
+             regenerate it with `runeweave schema > schemas/stack.schema.json`"
+        );
+    }
+
+    #[test]
+    fn test_examples_match_schema() {
+        for example in ["examples/simple.json", "examples/polyglot.json"] {
+            let plan = runeweave::verify::verify_plan(std::path::Path::new(example));
+            assert!(plan.is_ok(), "{example}: {:?}", plan.err());
+        }
+    }
+
+    #[test]
+    fn test_deny_rule_version_semantics() {
+        use runeweave::dependency::{DenyRule, Dependency, Ecosystem};
+
+        let cases = [
+            // (ecosystem, deny rule, dependency, denied)
+            (Ecosystem::Crates, "openssl-sys", "openssl_sys@0.9", true),
+            (Ecosystem::Crates, "time@<0.3", "time@0.2", true),
+            (Ecosystem::Crates, "time@<0.3", "time@0.3", false),
+            (
+                Ecosystem::Crates,
+                "time@>=0.2, <0.2.23",
+                "time@^0.2.25",
+                false,
+            ),
+            (Ecosystem::Npm, "left-pad@*", "left-pad@^1.3.0", true),
+            (Ecosystem::Npm, "lodash@<4.17.21", "lodash@4.17.21", false),
+            (Ecosystem::Npm, "lodash@<4.17.21", "lodash@~4.17.0", true),
+            (
+                Ecosystem::Npm,
+                "lodash@1.x || >=3 <3.5",
+                "lodash@3.4.1",
+                true,
+            ),
+            (
+                Ecosystem::Npm,
+                "@scope/pkg@1.0.0 - 1.2",
+                "@scope/pkg@1.2.9",
+                true,
+            ),
+            (Ecosystem::PyPI, "cryptography<42.0", "cryptography", true),
+            (
+                Ecosystem::PyPI,
+                "cryptography<42.0",
+                "cryptography>=42",
+                false,
+            ),
+            (
+                Ecosystem::PyPI,
+                "cryptography<42.0",
+                "Cryptography==41.0.7",
+                true,
+            ),
+            (Ecosystem::PyPI, "django~=3.2.0", "django@^3.2.5", true),
+            (Ecosystem::PyPI, "urllib3!=2.0.*", "urllib3==2.0.4", false),
+        ];
+
+        for (ecosystem, rule, dep, denied) in cases {
+            let rule = DenyRule::parse(ecosystem, rule).unwrap();
+            let dep = Dependency::parse(ecosystem, dep).unwrap();
+            assert_eq!(rule.matches(&dep), denied, "{} vs {dep}", rule.spec);
+        }
+    }
+
+    #[test]
+    fn test_deny_lists_cover_template_dependencies() {
+        let (_dir, plan_path) = create_test_plan();
+        let plan = runeweave::verify::verify_plan(&plan_path).unwrap();
+
+        let deny: runeweave::verify::DenyPolicy =
+            serde_yaml::from_str("crates: [\"actix-web@4\"]\nnpm: [\"hono@<4\", \"left-pad@*\"]")
+                .unwrap();
+        let violations = runeweave::verify::check_deny_lists(&deny, &plan).unwrap();
+
+        let found: Vec<_> = violations
+            .iter()
+            .map(|v| (v.rule.as_str(), v.location.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("deny.crates", "services/api"),
+                ("deny.npm", "services/api-edge"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_malformed_dependencies_fail_verify() {
+        let dir = TempDir::new().unwrap();
+        let plan_path = write_plan(
+            dir.path(),
+            r#"{
+                "project": "deps",
+                "services": [
+                    {"name": "api", "language": "rust", "dependencies": ["serde@not a version"]},
+                    {"name": "job", "language": "go", "dependencies": ["github.com/pkg/errors"]},
+                    {"name": "svc", "language": "java", "dependencies": ["com.google.guava:guava"]},
+                    {"name": "web", "language": "node", "dependencies": ["hono"]}
+                ],
+                "toolchain": {}
+            }"#,
+        );
+
+        let err = runeweave::verify::verify_plan(&plan_path).unwrap_err();
+        assert!(
+            matches!(err, runeweave::error::WeaveError::Plan { .. }),
+            "{err}"
+        );
+        assert_eq!(err.exit_code(), 1);
+        let message = err.to_string();
+        for expected in [
+            "service 'api': Invalid crate dependency 'serde@not a version'",
+            "service 'job': Invalid Go module dependency 'github.com/pkg/errors': a version is required",
+            "service 'svc': Invalid Maven artifact dependency 'com.google.guava:guava': a version is required",
+        ] {
+            assert!(message.contains(expected), "{message}");
+        }
+        assert!(!message.contains("service 'web'"), "{message}");
+    }
+
+    #[test]
+    fn test_spdx_expressions() {
+        use runeweave::license::{license_ids, license_matches};
+
+        assert_eq!(
+            license_ids("(MIT OR Apache-2.0) AND BSD-3-Clause").unwrap(),
+            vec!["MIT", "Apache-2.0", "BSD-3-Clause"]
+        );
+        assert_eq!(
+            license_ids("GPL-2.0-or-later WITH Classpath-exception-2.0").unwrap(),
+            vec!["GPL-2.0-or-later"]
+        );
+        for invalid in ["", "MIT OR", "(MIT", "MIT Apache-2.0", "MIT WITH"] {
+            assert!(license_ids(invalid).is_err(), "{invalid:?}");
+        }
+
+        assert!(license_matches("AGPL-3.0-or-later", "AGPL-3.0"));
+        assert!(license_matches("agpl-3.0+", "AGPL-3.0-only"));
+        assert!(!license_matches("LGPL-3.0", "GPL-3.0"));
+    }
+
+    #[test]
+    fn test_denied_license_fails_policy() {
+        let (dir, plan_path) = create_test_plan();
+        let plan = runeweave::verify::verify_plan(&plan_path).unwrap();
+
+        fs::write(
+            dir.path().join("licenses.yml"),
+            "crates:\n  actix-web: \"MIT OR AGPL-3.0-only\"\n",
+        )
+        .unwrap();
+        let policy_path = dir.path().join("policy.yml");
+        fs::write(
+            &policy_path,
+            "version: 1\ndeny:\n  licenses: [\"AGPL-3.0\"]\n  license_db: licenses.yml\n",
+        )
+        .unwrap();
+
+        let err = runeweave::verify::verify_policy(Some(&policy_path), &plan).unwrap_err();
+        let runeweave::error::WeaveError::Policy(violations) = &err else {
+            panic!("expected a policy failure, got {err}");
+        };
+        assert_eq!(err.exit_code(), 2);
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].rule, "deny.licenses");
+        assert_eq!(violations[0].location, "services/api");
+
+        // The bundled database alone has no AGPL dependencies for this plan
+        fs::write(
+            &policy_path,
+            "version: 1\ndeny:\n  licenses: [\"AGPL-3.0\"]\n",
+        )
+        .unwrap();
+        assert!(runeweave::verify::verify_policy(Some(&policy_path), &plan).is_ok());
+
+        // A dependency missing from the database fails unless allowed
+        fs::write(dir.path().join("licenses.yml"), "crates: {}\n").unwrap();
+        let mut plan = plan;
+        plan.services[0].dependencies = vec!["left-pad-rs@1".to_string()];
+        fs::write(
+            &policy_path,
+            "version: 1\ndeny:\n  licenses: [\"AGPL-3.0\"]\n",
+        )
+        .unwrap();
+        let err = runeweave::verify::verify_policy(Some(&policy_path), &plan).unwrap_err();
+        assert_eq!(err.exit_code(), 2);
+        assert!(err.to_string().contains("no license known for"), "{err}");
+
+        fs::write(
+            &policy_path,
+            "version: 1\ndeny:\n  licenses: [\"AGPL-3.0\"]\n  allow_unknown_licenses: true\n",
+        )
+        .unwrap();
+        let (_, warnings) = runeweave::verify::verify_policy(Some(&policy_path), &plan).unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].severity, runeweave::verify::Severity::Warning);
+    }
+
+    #[test]
+    fn test_conflicting_pins_fail_policy() {
+        let (_dir, plan_path) = create_test_plan();
+        let plan = runeweave::verify::verify_plan(&plan_path).unwrap();
+
+        let pin: runeweave::verify::PinPolicy = serde_yaml::from_str(
+            "rust: {msrv: \"1.80\"}\nnode: {version: \"22\"}\npython: {version: \"3.12.6\"}",
+        )
+        .unwrap();
+        let violations = runeweave::verify::check_pins(&pin, &plan);
+
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].rule, "pin.rust.msrv");
+        assert_eq!(violations[0].location, "toolchain/rust");
+    }
+
+    #[test]
+    fn test_pins_apply_to_generated_files() {
+        let dir = TempDir::new().unwrap();
+        let plan_path = write_plan(
+            dir.path(),
+            r#"{
+                "project": "pinned",
+                "services": [
+                    {"name": "api", "language": "rust", "framework": "actix", "runtime": null, "dependencies": []},
+                    {"name": "job", "language": "python", "framework": "fastapi", "runtime": null, "dependencies": []},
+                    {"name": "svc", "language": "go", "framework": null, "runtime": null, "dependencies": []},
+                    {"name": "app", "language": "java", "framework": "spring", "runtime": null, "dependencies": []}
+                ],
+                "toolchain": {"rust": {"version": "1.82", "targets": []}, "python": {"version": "3.12"}}
+            }"#,
+        );
+
+        let mut plan = runeweave::verify::verify_plan(&plan_path).unwrap();
+        let pin: runeweave::verify::PinPolicy = serde_yaml::from_str(
+            "rust: {msrv: \"1.82.0\"}\npython: {version: \"3.12.6\"}\ngo: {version: \"1.22.5\"}\njava: {version: \"17\"}",
+        )
+        .unwrap();
+        assert!(runeweave::verify::check_pins(&pin, &plan).is_empty());
+        runeweave::verify::apply_pins(&mut plan, &pin);
+
+        let out = dir.path().join("out");
+        let ctx = runeweave::render::RenderContext::new(plan, 1, &out);
+        runeweave::render::render_templates(&ctx).unwrap();
+
+        let read = |path: &str| fs::read_to_string(out.join(path)).unwrap();
+        assert!(read("toolchain/rust-toolchain.toml").contains("channel = \"1.82.0\""));
+        assert_eq!(read("toolchain/.python-version"), "3.12.6");
+        assert_eq!(read("toolchain/.java-version"), "17");
+        assert!(read("toolchain/go.mod").contains("go 1.22.5"));
+        assert!(read("services/api/Cargo.toml").contains("rust-version = \"1.82.0\""));
+        assert!(read("services/job/pyproject.toml").contains("python = \"^3.12.6\""));
+        assert!(read("services/svc/go.mod").contains("go 1.22.5"));
+        assert!(read("services/app/pom.xml").contains("<maven.compiler.source>17<"));
+    }
+
     #[test]
     fn test_kebab_case_validation() {
-        // These tests check the internal validation logic
+        use runeweave::naming::NamingConvention;
+
         let valid_names = vec!["test-project", "api-edge", "my-service-123"];
         let invalid_names = vec![
             "TestProject",
@@ -65,20 +431,1374 @@ mod tests {
         ];
 
         for name in valid_names {
-            // Would test the is_kebab_case function if it were public
-            assert!(name
-                .chars()
-                .all(|c| c.is_lowercase() || c.is_numeric() || c == '-'));
+            assert!(NamingConvention::KebabCase.matches(name), "{name}");
         }
 
         for name in invalid_names {
-            // These should fail kebab-case validation
+            assert!(!NamingConvention::KebabCase.matches(name), "{name}");
+        }
+    }
+
+    #[test]
+    fn test_naming_policy_conventions_and_suggestions() {
+        use runeweave::naming::NamingConvention;
+
+        assert!(NamingConvention::SnakeCase.matches("job_runner2"));
+        assert!(NamingConvention::LowerDot.matches("com.example.api"));
+        assert_eq!(
+            NamingConvention::SnakeCase.convert("apiGateway-v2"),
+            "api_gateway_v2"
+        );
+
+        let plan: runeweave::verify::StackPlan = serde_json::from_str(
+            r#"{
+                "project": "MyProject",
+                "services": [
+                    {"name": "job_runner", "language": "python", "framework": null, "runtime": null, "dependencies": []},
+                    {"name": "apiGateway", "language": "go", "framework": null, "runtime": null, "dependencies": []},
+                    {"name": "edge.api", "language": "rust", "framework": null, "runtime": null, "dependencies": []}
+                ],
+                "toolchain": {}
+            }"#,
+        )
+        .unwrap();
+
+        let naming: runeweave::verify::NamingPolicy =
+            serde_yaml::from_str("project: snake_case\nservice:\n  regex: '[a-z]+([._][a-z]+)*'")
+                .unwrap();
+        let violations = runeweave::verify::check_naming(&naming, &plan).unwrap();
+        let messages: Vec<_> = violations.iter().map(|v| v.to_string()).collect();
+
+        assert_eq!(messages.len(), 3, "{messages:#?}");
+        assert!(
+            messages[0].contains("did you mean 'my_project'?"),
+            "{}",
+            messages[0]
+        );
+        assert!(
+            messages[1].contains("did you mean 'api_gateway'?"),
+            "{}",
+            messages[1]
+        );
+        // Matches the regex, but '.' is not allowed in a Cargo package name
+        assert!(
+            messages[2].contains("Cargo package names"),
+            "{}",
+            messages[2]
+        );
+        assert!(
+            messages[2].contains("did you mean 'edge_api'?"),
+            "{}",
+            messages[2]
+        );
+    }
+
+    #[test]
+    fn test_template_hash_covers_every_template() {
+        let hash = runeweave::render::template_hash(&Default::default());
+        assert_eq!(hash.len(), 64);
+        assert_eq!(hash, runeweave::render::template_hash(&Default::default()));
+
+        // Templates are hashed by name, so no pack may shadow another's.
+        // Overriding one with its own source changes nothing, editing any
+        // one of them changes the hash.
+        let dir = TempDir::new().unwrap();
+        let hash_with = |name: &str, source: &str| {
+            let path = dir.path().join(name);
+            fs::write(&path, source).unwrap();
+            let overrides = runeweave::render::load_templates(dir.path()).unwrap();
+            fs::remove_file(&path).unwrap();
+            runeweave::render::template_hash(&overrides)
+        };
+        let mut names = std::collections::HashSet::new();
+        for pack in runeweave::language_pack::language_packs() {
+            for (name, source) in pack.templates() {
+                assert!(names.insert(name), "template {name} registered twice");
+                assert_eq!(hash_with(name, source), hash, "{name}");
+                assert_ne!(
+                    hash_with(name, &format!("{source}# edited\n")),
+                    hash,
+                    "{name}"
+                );
+            }
+        }
+        assert!(names.contains("java-application.java"));
+        assert_ne!(hash_with("ci.yml", "name: edited\n"), hash);
+    }
+
+    // Every file below `root`, keyed by its path relative to `root`
+    fn read_tree(root: &std::path::Path) -> std::collections::BTreeMap<PathBuf, Vec<u8>> {
+        let mut files = std::collections::BTreeMap::new();
+        let mut dirs = vec![root.to_path_buf()];
+        while let Some(dir) = dirs.pop() {
+            for entry in fs::read_dir(dir).unwrap() {
+                let path = entry.unwrap().path();
+                if path.is_dir() {
+                    dirs.push(path);
+                } else {
+                    let relative = path.strip_prefix(root).unwrap().to_path_buf();
+                    files.insert(relative, fs::read(&path).unwrap());
+                }
+            }
+        }
+        files
+    }
+
+    #[test]
+    fn test_same_plan_and_seed_render_identical_trees() {
+        let dir = TempDir::new().unwrap();
+        let plan_path = PathBuf::from("examples/polyglot.json");
+        let plan_content = fs::read_to_string(&plan_path).unwrap();
+
+        let render = |name: &str| {
+            let out = dir.path().join(name);
+            let ctx = render_context(&plan_path, 42, &out);
+            let rendered = runeweave::render::render_templates(&ctx).unwrap();
+            let manifest = runeweave::manifest::generate_manifest(
+                &plan_content,
+                42,
+                "1.82",
+                &runeweave::render::template_hash(&Default::default()),
+                runeweave::manifest::hash_files(&out, &rendered.files).unwrap(),
+                rendered.ports,
+            )
+            .unwrap();
+            runeweave::manifest::write_manifest(&manifest, &out).unwrap();
+            read_tree(&out)
+        };
+
+        let first = render("first");
+        let second = render("second");
+        assert!(first.contains_key(&PathBuf::from("weave.manifest.json")));
+        assert_eq!(
+            first.keys().collect::<Vec<_>>(),
+            second.keys().collect::<Vec<_>>()
+        );
+        for (path, content) in &first {
             assert!(
-                name.chars().any(|c| c.is_uppercase())
-                    || name.starts_with('-')
-                    || name.ends_with('-')
-                    || name.contains("--")
+                second[path] == *content,
+                "{} differs between runs",
+                path.display()
+            );
+        }
+    }
+
+    #[test]
+    fn test_check_reports_drift_from_manifest() {
+        let (dir, plan_path) = create_test_plan();
+        let out = dir.path().join("out");
+        let ctx = render_context(&plan_path, 7, &out);
+        let rendered = runeweave::render::render_templates(&ctx).unwrap();
+        let manifest = runeweave::manifest::generate_manifest(
+            &fs::read_to_string(&plan_path).unwrap(),
+            7,
+            "1.82",
+            &runeweave::render::template_hash(&Default::default()),
+            runeweave::manifest::hash_files(&out, &rendered.files).unwrap(),
+            rendered.ports,
+        )
+        .unwrap();
+        assert_eq!(manifest.files.len(), rendered.files.len());
+        assert!(manifest.files.contains_key("services/api/Cargo.toml"));
+        runeweave::manifest::write_manifest(&manifest, &out).unwrap();
+
+        let drift = runeweave::manifest::check_scaffold(&out).unwrap();
+        assert!(drift.is_empty(), "{drift:?}");
+
+        fs::write(out.join("services/api/src/main.rs"), "fn main() {}\n").unwrap();
+        fs::remove_file(out.join("toolchain/.node-version")).unwrap();
+        fs::write(out.join("services/api/README.md"), "notes\n").unwrap();
+        fs::create_dir_all(out.join(".git")).unwrap();
+        fs::write(out.join(".git/HEAD"), "ref: refs/heads/main\n").unwrap();
+        // Files of the surrounding project are not part of the scaffold
+        fs::write(out.join("README.md"), "project\n").unwrap();
+        fs::create_dir_all(out.join("docs")).unwrap();
+        fs::write(out.join("docs/intro.md"), "intro\n").unwrap();
+
+        let drift = runeweave::manifest::check_scaffold(&out).unwrap();
+        assert_eq!(drift.modified, vec!["services/api/src/main.rs"]);
+        assert_eq!(drift.deleted, vec!["toolchain/.node-version"]);
+        assert_eq!(drift.added, vec!["services/api/README.md"]);
+
+        // A manifest from before file hashes were recorded cannot pass
+        let manifest_path = out.join("weave.manifest.json");
+        let mut legacy: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&manifest_path).unwrap()).unwrap();
+        legacy.as_object_mut().unwrap().remove("files");
+        fs::write(&manifest_path, legacy.to_string()).unwrap();
+        let err = runeweave::manifest::check_scaffold(&out).unwrap_err();
+        assert!(
+            err.to_string().contains("does not record file hashes"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_seeded_template_values() {
+        let render = |seed: u64| {
+            let mut tera = tera::Tera::default();
+            runeweave::seed::SeededValues::new(seed).register(&mut tera);
+            tera.render_str(
+                "{{ uuid() }} {{ port() }} {{ port() }} {{ secret() }} {{ secret(length=8) }}",
+                &tera::Context::new(),
+            )
+            .unwrap()
+        };
+
+        let first = render(42);
+        assert_eq!(first, render(42));
+        assert_ne!(first, render(43));
+
+        let values: Vec<_> = first.split(' ').collect();
+        let uuid: Vec<_> = values[0].split('-').map(str::len).collect();
+        assert_eq!(uuid, vec![8, 4, 4, 4, 12]);
+        assert_eq!(&values[0][14..15], "4");
+        assert_ne!(values[1], values[2]);
+        assert_eq!(values[3].len(), 32);
+        assert_eq!(values[4].len(), 8);
+
+        let seeded = runeweave::seed::SeededValues::new(1);
+        let ports: std::collections::HashSet<_> =
+            (0..1000).map(|_| seeded.port().unwrap()).collect();
+        assert_eq!(ports.len(), 1000);
+        assert!(ports
+            .iter()
+            .all(|p| runeweave::seed::PORT_RANGE.contains(p)));
+
+        // A template that draws more ports than the range holds fails to
+        // render instead of aborting
+        let (dir, plan_path) = create_test_plan();
+        let ctx = runeweave::render::RenderContext {
+            template_overrides: [(
+                "ci.yml".to_string(),
+                "{% for i in range(end=10001) %}{{ port() }}{% endfor %}".to_string(),
+            )]
+            .into(),
+            ..render_context(&plan_path, 1, &dir.path().join("out"))
+        };
+        let err = runeweave::render::render_templates(&ctx).err().unwrap();
+        assert_eq!(err.exit_code(), 1);
+        assert!(format!("{err}").contains("is taken"), "{err}");
+    }
+
+    #[test]
+    fn test_service_ports_are_unique() {
+        let dir = TempDir::new().unwrap();
+        let plan_path = dir.path().join("plan.json");
+        let write_plan = |services: &str| {
+            fs::write(
+                &plan_path,
+                format!(r#"{{"project": "ports", "services": [{services}], "toolchain": {{}}}}"#),
+            )
+            .unwrap();
+        };
+
+        write_plan(
+            r#"{"name": "a", "language": "rust", "dependencies": []},
+               {"name": "b", "language": "go", "dependencies": []},
+               {"name": "c", "language": "python", "framework": "fastapi", "dependencies": [], "port": 8080}"#,
+        );
+        let out = dir.path().join("out");
+        let ctx = render_context(&plan_path, 3, &out);
+        let ports = runeweave::render::render_templates(&ctx).unwrap().ports;
+
+        assert_eq!(ports["c"], 8080);
+        let unique: std::collections::HashSet<_> = ports.values().collect();
+        assert_eq!(unique.len(), 3, "{ports:?}");
+        let main_go = fs::read_to_string(out.join("services/b/main.go")).unwrap();
+        assert!(main_go.contains(&format!(":{}", ports["b"])), "{main_go}");
+        let main_py = fs::read_to_string(out.join("services/c/src/main.py")).unwrap();
+        assert!(main_py.contains("port=8080"), "{main_py}");
+
+        write_plan(
+            r#"{"name": "a", "language": "rust", "dependencies": [], "port": 9000},
+               {"name": "b", "language": "go", "dependencies": [], "port": 9000}"#,
+        );
+        let err = runeweave::verify::verify_plan(&plan_path).unwrap_err();
+        assert!(err.to_string().contains("port 9000"), "{err}");
+
+        // Ports outside 1..=65535 are schema violations at the port itself
+        write_plan(
+            r#"{"name": "a", "language": "rust", "dependencies": [], "port": 0},
+               {"name": "b", "language": "go", "dependencies": [], "port": 70000}"#,
+        );
+        match runeweave::verify::verify_plan(&plan_path).unwrap_err() {
+            runeweave::error::WeaveError::Schema { violations, .. } => {
+                let located: Vec<_> = violations
+                    .iter()
+                    .map(|v| (v.pointer.as_str(), v.line))
+                    .collect();
+                assert_eq!(
+                    located,
+                    vec![("/services/0/port", 1), ("/services/1/port", 2)]
+                );
+                assert!(violations[1].message.contains("maximum of 65535"));
+            }
+            err => panic!("expected a schema violation, got {err}"),
+        }
+    }
+
+    #[test]
+    fn test_dotnet_pack_renders_service_and_tests() {
+        let dir = TempDir::new().unwrap();
+        let plan_path = write_plan(
+            dir.path(),
+            r#"{
+                "project": "dotnet-demo",
+                "services": [
+                    {"name": "api", "language": "dotnet", "dependencies": ["Serilog@3.1.1"]}
+                ],
+                "toolchain": {"dotnet": {"version": "8.0"}}
+            }"#,
+        );
+
+        let out = dir.path().join("out");
+        let ctx = render_context(&plan_path, 1, &out);
+        let rendered = runeweave::render::render_templates(&ctx).unwrap();
+        assert!(!rendered.files.iter().any(|f| f.ends_with("Cargo.toml")));
+
+        let read = |path: &str| fs::read_to_string(out.join(path)).unwrap();
+        let csproj = read("services/api/src/api.csproj");
+        assert!(csproj.contains("<TargetFramework>net8.0</TargetFramework>"));
+        assert!(csproj.contains(r#"<PackageReference Include="Serilog" Version="3.1.1" />"#));
+        assert!(!csproj.contains("xunit"));
+        assert!(read("services/api/src/Program.cs").contains("MapGet(\"/healthz\""));
+        assert!(read("services/api/src/Program.cs").contains("0.0.0.0:5000"));
+
+        let tests = read("services/api/tests/api.Tests.csproj");
+        assert!(tests.contains(r#"<PackageReference Include="xunit" Version="2.9.0" />"#));
+        assert!(tests.contains(r#"<ProjectReference Include="../src/api.csproj" />"#));
+        assert!(read("services/api/tests/HealthzTests.cs").contains("[Fact]"));
+
+        let global_json: serde_json::Value =
+            serde_json::from_str(&read("toolchain/global.json")).unwrap();
+        assert_eq!(global_json["sdk"]["version"], "8.0.100");
+
+        let ci = read(".github/workflows/ci.yml");
+        assert!(ci.contains("actions/setup-dotnet@v4"));
+        assert!(ci.contains("dotnet test tests"));
+
+        // The build check restores from the local package cache, never nuget.org
+        let service = &ctx.plan.services[0];
+        let commands: Vec<_> = runeweave::language_pack::get_language_pack(&service.language)
+            .unwrap()
+            .check_commands(service)
+            .iter()
+            .map(|c| c.args.join(" "))
+            .collect();
+        assert_eq!(commands.len(), 2);
+        assert!(commands[0].starts_with("restore tests --source "));
+        assert_eq!(commands[1], "build tests --nologo --no-restore");
+    }
+
+    #[test]
+    fn test_deno_pack_renders_server_and_deploy_config() {
+        let dir = TempDir::new().unwrap();
+        let plan_path = write_plan(
+            dir.path(),
+            r#"{
+                "project": "deno-demo",
+                "services": [
+                    {"name": "api", "language": "deno", "dependencies": ["zod@^3.23.0"]},
+                    {"name": "edge", "language": "deno", "runtime": "deno-deploy", "dependencies": []}
+                ],
+                "toolchain": {"deno": {"version": "2.0.0"}}
+            }"#,
+        );
+
+        let out = dir.path().join("out");
+        let ctx = render_context(&plan_path, 1, &out);
+        let rendered = runeweave::render::render_templates(&ctx).unwrap();
+        let read = |path: &str| fs::read_to_string(out.join(path)).unwrap();
+
+        let config: serde_json::Value =
+            serde_json::from_str(&read("services/api/deno.json")).unwrap();
+        assert_eq!(config["imports"]["zod"], "npm:zod@^3.23.0");
+        assert!(config.get("deploy").is_none());
+        let main = read("services/api/main.ts");
+        assert!(main.contains("\"/healthz\""));
+        assert!(main.contains(&format!("port: {}", rendered.ports["api"])));
+        assert!(read("services/api/main_test.ts").contains("Deno.test("));
+
+        let config: serde_json::Value =
+            serde_json::from_str(&read("services/edge/deno.json")).unwrap();
+        assert_eq!(config["deploy"]["entrypoint"], "main.ts");
+        assert_eq!(config["tasks"]["deploy"], "deployctl deploy");
+        assert!(read("services/edge/main.ts").contains("Deno.serve(handler);"));
+
+        assert_eq!(read("toolchain/.deno-version"), "2.0.0");
+        assert!(read(".github/workflows/ci.yml").contains("denoland/setup-deno@v2"));
+    }
+
+    #[test]
+    fn test_unsupported_combinations_are_rejected() {
+        let dir = TempDir::new().unwrap();
+        let plan_path = write_plan(
+            dir.path(),
+            r#"{
+                "project": "combos",
+                "services": [
+                    {"name": "web", "language": "node", "framework": "hono", "dependencies": []},
+                    {"name": "api", "language": "rust", "framework": "rocket", "dependencies": []},
+                    {"name": "job", "language": "go", "framework": "gin", "runtime": "lambda", "dependencies": []}
+                ],
+                "toolchain": {}
+            }"#,
+        );
+
+        let err = runeweave::verify::verify_plan(&plan_path).unwrap_err();
+        assert_eq!(err.exit_code(), 1);
+        let message = err.to_string();
+        assert!(
+            message.contains(
+                "service 'api': framework 'rocket' is not supported for rust (supported: actix, worker)"
+            ),
+            "{message}"
+        );
+        assert!(
+            message.contains(
+                "service 'job': runtime 'lambda' is not supported for go gin (supported: native)"
+            ),
+            "{message}"
+        );
+        assert!(!message.contains("service 'web'"), "{message}");
+
+        // Defaults come from the first registered capability
+        let mut service: runeweave::verify::Service = serde_json::from_str(
+            r#"{"name": "web", "language": "node", "framework": "hono", "dependencies": []}"#,
+        )
+        .unwrap();
+        runeweave::language_pack::resolve_capability(&mut service).unwrap();
+        assert_eq!(service.runtime.as_deref(), Some("cloudflare"));
+    }
+
+    #[test]
+    fn test_template_overrides_replace_built_ins() {
+        let (dir, plan_path) = create_test_plan();
+        let templates = dir.path().join("templates");
+        fs::create_dir_all(&templates).unwrap();
+        fs::write(
+            templates.join("rust-cargo.toml"),
+            "[package]\nname = \"{{ service_name }}\"\n# platform template\n",
+        )
+        .unwrap();
+        fs::write(templates.join("ci.yml"), "name: platform-ci\n").unwrap();
+
+        let overrides = runeweave::render::load_templates(&templates).unwrap();
+        assert_eq!(
+            overrides.keys().collect::<Vec<_>>(),
+            vec!["ci.yml", "rust-cargo.toml"]
+        );
+
+        let out = dir.path().join("out");
+        let ctx = runeweave::render::RenderContext {
+            template_overrides: overrides.clone(),
+            ..render_context(&plan_path, 1, &out)
+        };
+        runeweave::render::render_templates(&ctx).unwrap();
+
+        let read = |path: &str| fs::read_to_string(out.join(path)).unwrap();
+        assert_eq!(
+            read("services/api/Cargo.toml"),
+            "[package]\nname = \"api\"\n# platform template\n"
+        );
+        assert_eq!(read(".github/workflows/ci.yml"), "name: platform-ci\n");
+        // Templates without an override are untouched
+        assert!(read("services/api-edge/package.json").contains("\"hono\""));
+
+        assert_ne!(
+            runeweave::render::template_hash(&overrides),
+            runeweave::render::template_hash(&Default::default())
+        );
+
+        // A misnamed override would silently change nothing
+        fs::write(templates.join("rust-cargo.tml"), "[package]\n").unwrap();
+        let err = runeweave::render::load_templates(&templates).unwrap_err();
+        assert!(err.to_string().contains("rust-cargo.tml"), "{err}");
+    }
+
+    #[cfg(unix)]
+    fn write_plugin(dir: &std::path::Path, name: &str, script: &str) {
+        use std::os::unix::fs::PermissionsExt;
+        let path = dir.join(name);
+        fs::write(&path, script).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_plugin_pack_renders_service() {
+        let dir = TempDir::new().unwrap();
+        let plugins = dir.path().join("plugins");
+        fs::create_dir_all(&plugins).unwrap();
+        // Echoes the render request back as a file
+        write_plugin(
+            &plugins,
+            "elixir",
+            r#"#!/bin/sh
+case "$1" in
+  describe)
+    echo '{"language": "elixir", "capabilities": [{"framework": "phoenix", "runtimes": ["beam"]}], "default_port": 4000}'
+    ;;
+  render)
+    request=$(sed 's/\\/\\\\/g; s/"/\\"/g')
+    printf '{"files": [{"path": "mix.exs", "content": "defmodule Api.MixProject do\\nend\\n"}, {"path": "request.json", "content": "%s"}]}' "$request"
+    ;;
+esac
+"#,
+        );
+        // Not executable, so not a plugin
+        fs::write(plugins.join("README.md"), "elixir plugin").unwrap();
+        runeweave::plugin::register_plugins(&plugins).unwrap();
+        assert!(runeweave::plugin::find("elixir").is_some());
+        // The registry is process-wide, so every test registers its own language
+        let elsewhere = std::thread::spawn(|| runeweave::plugin::find("elixir").is_some());
+        assert!(elsewhere.join().unwrap());
+
+        let plan_path = write_plan(
+            dir.path(),
+            r#"{
+                "project": "beam-app",
+                "services": [{"name": "api", "language": "elixir", "dependencies": []}],
+                "toolchain": {}
+            }"#,
+        );
+        let plan = runeweave::verify::verify_plan(&plan_path).unwrap();
+        assert_eq!(plan.services[0].framework.as_deref(), Some("phoenix"));
+        assert_eq!(plan.services[0].runtime.as_deref(), Some("beam"));
+
+        let out = dir.path().join("out");
+        let ctx = runeweave::render::RenderContext::new(plan, 7, &out);
+        let rendered = runeweave::render::render_templates(&ctx).unwrap();
+        assert_eq!(rendered.ports["api"], 4000);
+        assert_eq!(
+            fs::read_to_string(out.join("services/api/mix.exs")).unwrap(),
+            "defmodule Api.MixProject do\nend\n"
+        );
+
+        let request: serde_json::Value = serde_json::from_str(
+            &fs::read_to_string(out.join("services/api/request.json")).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(request["service"]["name"], "api");
+        assert_eq!(request["service"]["language"], "elixir");
+        assert_eq!(request["context"]["project"], "beam-app");
+        assert_eq!(request["context"]["service_port"], 4000);
+
+        // Unknown languages still fail verification
+        fs::write(
+            &plan_path,
+            r#"{"project": "x", "services": [{"name": "api", "language": "cobol", "dependencies": []}], "toolchain": {}}"#,
+        )
+        .unwrap();
+        let err = runeweave::verify::verify_plan(&plan_path).unwrap_err();
+        assert!(
+            err.to_string().contains("no language pack for 'cobol'"),
+            "{err}"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_plugin_cannot_write_outside_its_service() {
+        let dir = TempDir::new().unwrap();
+        write_plugin(
+            dir.path(),
+            "kotlin",
+            r#"#!/bin/sh
+case "$1" in
+  describe)
+    echo '{"language": "kotlin", "capabilities": [{"framework": "ktor", "runtimes": ["jvm"]}], "default_port": 8080}'
+    ;;
+  render)
+    echo '{"files": [{"path": "../../escaped.txt", "content": "x"}]}'
+    ;;
+esac
+"#,
+        );
+        runeweave::plugin::register_plugins(dir.path()).unwrap();
+
+        let plan_path = write_plan(
+            dir.path(),
+            r#"{"project": "x", "services": [{"name": "api", "language": "kotlin", "dependencies": []}], "toolchain": {}}"#,
+        );
+        let ctx = render_context(&plan_path, 7, &dir.path().join("out"));
+        let err = runeweave::render::render_templates(&ctx)
+            .err()
+            .expect("plugin wrote outside its service directory");
+        assert!(err.to_string().contains("outside services/api"), "{err}");
+        assert!(!dir.path().join("escaped.txt").exists());
+    }
+
+    #[cfg(feature = "wasm-plugins")]
+    /// A WASI module that describes a `gleam` pack and, on `render`, writes
+    /// `main.gleam` plus its request to `/out`. It exits with code 2 if it
+    /// manages to create a file outside `/out`.
+    fn gleam_plugin_wasm() -> Vec<u8> {
+        let strings = [
+            r#"{"language": "gleam", "capabilities": [{"framework": "wisp", "runtimes": ["beam"]}], "default_port": 8000}"#,
+            "../escaped.txt",
+            "main.gleam",
+            "pub fn main() {}\n",
+            "request.json",
+        ];
+        let mut data = String::new();
+        let mut offsets = Vec::new();
+        let mut offset = 512;
+        for string in strings {
+            let escaped = string
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('\n', "\\n");
+            data.push_str(&format!("(data (i32.const {offset}) \"{escaped}\")\n"));
+            offsets.push((offset, string.len()));
+            offset += string.len() + 1;
+        }
+        let [describe, escape, main, content, request] = offsets[..] else {
+            unreachable!()
+        };
+
+        let wat = format!(
+            r#"(module
+  (import "wasi_snapshot_preview1" "args_get" (func $args_get (param i32 i32) (result i32)))
+  (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+  (import "wasi_snapshot_preview1" "fd_read" (func $fd_read (param i32 i32 i32 i32) (result i32)))
+  (import "wasi_snapshot_preview1" "fd_close" (func $fd_close (param i32) (result i32)))
+  (import "wasi_snapshot_preview1" "path_open"
+    (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+  (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+  (memory (export "memory") 2)
+  {data}
+  (func $write (param $fd i32) (param $ptr i32) (param $len i32)
+    (i32.store (i32.const 32) (local.get $ptr))
+    (i32.store (i32.const 36) (local.get $len))
+    (drop (call $fd_write (local.get $fd) (i32.const 32) (i32.const 1) (i32.const 8))))
+  ;; Create a file below the first preopen; the new fd is stored at 12
+  (func $create (param $ptr i32) (param $len i32) (result i32)
+    (call $path_open (i32.const 3) (i32.const 0) (local.get $ptr) (local.get $len)
+      (i32.const 9) (i64.const 64) (i64.const 0) (i32.const 0) (i32.const 12)))
+  (func (export "_start") (local $len i32)
+    (drop (call $args_get (i32.const 64) (i32.const 128)))
+    ;; argv[1] starts with 'd': describe
+    (if (i32.eq (i32.load8_u (i32.load (i32.const 68))) (i32.const 100))
+      (then
+        (call $write (i32.const 1) (i32.const {describe_ptr}) (i32.const {describe_len}))
+        (return)))
+    (if (i32.eqz (call $create (i32.const {escape_ptr}) (i32.const {escape_len})))
+      (then (call $proc_exit (i32.const 2))))
+    (drop (call $create (i32.const {main_ptr}) (i32.const {main_len})))
+    (call $write (i32.load (i32.const 12)) (i32.const {content_ptr}) (i32.const {content_len}))
+    (drop (call $fd_close (i32.load (i32.const 12))))
+    (block $done
+      (loop $read
+        (i32.store (i32.const 32) (i32.add (i32.const 4096) (local.get $len)))
+        (i32.store (i32.const 36) (i32.sub (i32.const 60000) (local.get $len)))
+        (drop (call $fd_read (i32.const 0) (i32.const 32) (i32.const 1) (i32.const 16)))
+        (br_if $done (i32.eqz (i32.load (i32.const 16))))
+        (local.set $len (i32.add (local.get $len) (i32.load (i32.const 16))))
+        (br $read)))
+    (drop (call $create (i32.const {request_ptr}) (i32.const {request_len})))
+    (call $write (i32.load (i32.const 12)) (i32.const 4096) (local.get $len))
+    (drop (call $fd_close (i32.load (i32.const 12))))))"#,
+            describe_ptr = describe.0,
+            describe_len = describe.1,
+            escape_ptr = escape.0,
+            escape_len = escape.1,
+            main_ptr = main.0,
+            main_len = main.1,
+            content_ptr = content.0,
+            content_len = content.1,
+            request_ptr = request.0,
+            request_len = request.1,
+        );
+        wat::parse_str(wat).unwrap()
+    }
+
+    #[cfg(not(feature = "wasm-plugins"))]
+    #[test]
+    fn test_wasm_plugin_needs_feature() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("gleam.wasm"), b"\0asm").unwrap();
+        let err = runeweave::plugin::register_plugins(dir.path()).unwrap_err();
+        assert!(format!("{err:#}").contains("wasm-plugins"), "{err:#}");
+    }
+
+    #[cfg(feature = "wasm-plugins")]
+    #[test]
+    fn test_wasm_plugin_renders_in_sandbox() {
+        let dir = TempDir::new().unwrap();
+        let plugins = dir.path().join("plugins");
+        fs::create_dir_all(&plugins).unwrap();
+        fs::write(plugins.join("gleam.wasm"), gleam_plugin_wasm()).unwrap();
+        runeweave::plugin::register_plugins(&plugins).unwrap();
+
+        let plan_path = write_plan(
+            dir.path(),
+            r#"{
+                "project": "beam-app",
+                "services": [{"name": "web", "language": "gleam", "dependencies": []}],
+                "toolchain": {}
+            }"#,
+        );
+        let plan = runeweave::verify::verify_plan(&plan_path).unwrap();
+        assert_eq!(plan.services[0].framework.as_deref(), Some("wisp"));
+
+        let out = dir.path().join("out");
+        let ctx = runeweave::render::RenderContext::new(plan, 7, &out);
+        let rendered = runeweave::render::render_templates(&ctx).unwrap();
+        assert_eq!(rendered.ports["web"], 8000);
+        assert_eq!(
+            fs::read_to_string(out.join("services/web/main.gleam")).unwrap(),
+            "pub fn main() {}\n"
+        );
+
+        let request: serde_json::Value = serde_json::from_str(
+            &fs::read_to_string(out.join("services/web/request.json")).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(request["service"]["name"], "web");
+        assert_eq!(request["context"]["service_port"], 8000);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_build_check_runs_pack_commands_outside_scaffold() {
+        let dir = TempDir::new().unwrap();
+        let plugins = dir.path().join("plugins");
+        fs::create_dir_all(&plugins).unwrap();
+        for (language, check) in [
+            ("zig", "touch zig-cache && echo compiled"),
+            ("nim", "echo main.nim: undeclared identifier >&2; exit 3"),
+        ] {
+            write_plugin(
+                &plugins,
+                language,
+                &format!(
+                    r#"#!/bin/sh
+case "$1" in
+  describe)
+    echo '{{"language": "{language}", "capabilities": [{{"framework": "std", "runtimes": ["native"]}}], "default_port": 9000, "checks": [["sh", "-c", "{check}"]]}}'
+    ;;
+  render)
+    echo '{{"files": [{{"path": "main.{language}", "content": ""}}]}}'
+    ;;
+esac
+"#
+                ),
+            );
+        }
+        runeweave::plugin::register_plugins(&plugins).unwrap();
+
+        let plan_path = write_plan(
+            dir.path(),
+            r#"{
+                "project": "checked",
+                "services": [
+                    {"name": "fast", "language": "zig", "dependencies": []},
+                    {"name": "broken", "language": "nim", "dependencies": []}
+                ],
+                "toolchain": {}
+            }"#,
+        );
+        let out = dir.path().join("out");
+        let ctx = render_context(&plan_path, 7, &out);
+        let rendered = runeweave::render::render_templates(&ctx).unwrap();
+
+        let checks =
+            runeweave::build_check::run_build_checks(&ctx.plan, &out, &rendered.files).unwrap();
+        assert_eq!(checks.len(), 2);
+        assert!(checks[0].success);
+        assert_eq!(checks[0].output, "compiled\n");
+        assert!(!checks[1].success);
+        assert!(checks[1].output.contains("undeclared identifier"));
+        // Build artifacts stay out of the scaffold
+        assert!(!out.join("services/fast/zig-cache").exists());
+
+        let err = runeweave::error::WeaveError::BuildCheck(checks);
+        assert_eq!(err.exit_code(), 1);
+        let message = err.to_string();
+        assert!(message.starts_with("Build check failed for 1 service(s):"));
+        assert!(message.contains("services/broken: failed (`sh -c"));
+        assert!(!message.contains("services/fast"));
+    }
+
+    /// Serve one request with `status` and `body`, handing the request's
+    /// method, URL, `Authorization` header and body back through the handle.
+    fn mock_forge(
+        status: u16,
+        body: &'static str,
+    ) -> (
+        String,
+        std::thread::JoinHandle<(String, String, String, String)>,
+    ) {
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", server.server_addr().to_ip().unwrap());
+        let handle = std::thread::spawn(move || {
+            let mut request = server.recv().unwrap();
+            let mut content = String::new();
+            request.as_reader().read_to_string(&mut content).unwrap();
+            let auth = request
+                .headers()
+                .iter()
+                .find(|h| h.field.equiv("Authorization"))
+                .map(|h| h.value.to_string())
+                .unwrap_or_default();
+            let seen = (
+                request.method().to_string(),
+                request.url().to_string(),
+                auth,
+                content,
             );
+            request
+                .respond(tiny_http::Response::from_string(body).with_status_code(status))
+                .unwrap();
+            seen
+        });
+        (url, handle)
+    }
+
+    #[test]
+    fn test_push_branch_and_open_pull_request() {
+        let dir = TempDir::new().unwrap();
+        let remote = dir.path().join("remote.git");
+        let remote_repo = git2::Repository::init_bare(&remote).unwrap();
+        let sig = git2::Signature::now("Dev", "dev@example.com").unwrap();
+        let readme = remote_repo.blob(b"# demo\n").unwrap();
+        let mut builder = remote_repo.treebuilder(None).unwrap();
+        builder.insert("README.md", readme, 0o100644).unwrap();
+        let tree = remote_repo.find_tree(builder.write().unwrap()).unwrap();
+        let initial = remote_repo
+            .commit(
+                Some("refs/heads/main"),
+                &sig,
+                &sig,
+                "Initial commit",
+                &tree,
+                &[],
+            )
+            .unwrap();
+        let out = dir.path().join("out");
+        fs::create_dir_all(out.join("services/api")).unwrap();
+        fs::write(out.join("services/api/Cargo.toml"), "[package]\n").unwrap();
+
+        let (api_url, server) = mock_forge(201, r#"{"html_url": "https://forge.test/o/r/pull/1"}"#);
+        let git_ops = runeweave::git::GitOps {
+            remote_url: remote.to_string_lossy().into_owned(),
+            forge: Some(Box::new(runeweave::git::GitHubClient {
+                api_url,
+                owner: "o".to_string(),
+                repo: "r".to_string(),
+                token: Some("secret".to_string()),
+            })),
+        };
+        let pr = runeweave::git::PullRequest {
+            title: "Scaffold demo".to_string(),
+            body: "seed: 1".to_string(),
+            head: "runeweave/demo-1".to_string(),
+            base: "main".to_string(),
+        };
+        let files = [std::path::PathBuf::from("services/api/Cargo.toml")];
+        let scaffold_commit = runeweave::git::ScaffoldCommit {
+            message: "Scaffold demo".to_string(),
+            ..Default::default()
+        };
+        let url = git_ops
+            .push_to_repo(&out, &files, &scaffold_commit, &pr)
+            .unwrap();
+        assert_eq!(url.as_deref(), Some("https://forge.test/o/r/pull/1"));
+
+        let commit = remote_repo
+            .find_reference("refs/heads/runeweave/demo-1")
+            .unwrap()
+            .peel_to_commit()
+            .unwrap();
+        assert_eq!(commit.message(), Some("Scaffold demo"));
+        // The branch builds on the remote's base branch
+        assert_eq!(commit.parent_id(0).unwrap(), initial);
+        let tree = commit.tree().unwrap();
+        assert!(tree.get_path("services/api/Cargo.toml".as_ref()).is_ok());
+        assert!(tree.get_path("README.md".as_ref()).is_ok());
+        // and is checked out with the base's files
+        assert_eq!(
+            fs::read_to_string(out.join("README.md")).unwrap(),
+            "# demo\n"
+        );
+        let local = git2::Repository::open(&out).unwrap();
+        assert!(local.statuses(None).unwrap().is_empty());
+
+        let (method, path, auth, body) = server.join().unwrap();
+        assert_eq!(method, "POST");
+        assert_eq!(path, "/repos/o/r/pulls");
+        assert_eq!(auth, "Bearer secret");
+        let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(body["head"], "runeweave/demo-1");
+        assert_eq!(body["base"], "main");
+        assert_eq!(body["title"], "Scaffold demo");
+
+        // A forge rejection is a git failure (exit code 3)
+        let (api_url, server) = mock_forge(422, r#"{"message": "Validation Failed"}"#);
+        let git_ops = runeweave::git::GitOps {
+            remote_url: remote.to_string_lossy().into_owned(),
+            forge: Some(Box::new(runeweave::git::GitHubClient {
+                api_url,
+                owner: "o".to_string(),
+                repo: "r".to_string(),
+                token: None,
+            })),
+        };
+        let out = dir.path().join("out-2");
+        fs::create_dir_all(&out).unwrap();
+        fs::write(out.join("README.md"), "demo\n").unwrap();
+        let pr = runeweave::git::PullRequest {
+            head: "runeweave/demo-2".to_string(),
+            ..pr
+        };
+        let err = git_ops
+            .push_to_repo(&out, &["README.md".into()], &scaffold_commit, &pr)
+            .unwrap_err();
+        assert_eq!(err.exit_code(), 3);
+        assert!(err.to_string().contains("Validation Failed"), "{err}");
+        server.join().unwrap();
+
+        // An empty remote gets the scaffold as its base branch, with no
+        // pull request to open
+        let empty = dir.path().join("empty.git");
+        let empty_repo = git2::Repository::init_bare(&empty).unwrap();
+        let git_ops = runeweave::git::GitOps {
+            remote_url: empty.to_string_lossy().into_owned(),
+            forge: Some(Box::new(runeweave::git::GitHubClient {
+                api_url: "http://127.0.0.1:9".to_string(),
+                owner: "o".to_string(),
+                repo: "r".to_string(),
+                token: None,
+            })),
+        };
+        let out = dir.path().join("out-3");
+        fs::create_dir_all(&out).unwrap();
+        fs::write(out.join("README.md"), "demo\n").unwrap();
+        let url = git_ops
+            .push_to_repo(&out, &["README.md".into()], &scaffold_commit, &pr)
+            .unwrap();
+        assert_eq!(url, None);
+        let pushed = empty_repo
+            .find_reference("refs/heads/main")
+            .unwrap()
+            .peel_to_commit()
+            .unwrap();
+        assert_eq!(pushed.parent_count(), 0);
+        assert!(empty_repo
+            .find_reference("refs/heads/runeweave/demo-2")
+            .is_err());
+    }
+
+    #[test]
+    fn test_repo_specs() {
+        let remote = |spec: &str| runeweave::git::GitOps::new(spec).map(|ops| ops.remote_url);
+        assert_eq!(remote("github:o/r").unwrap(), "https://github.com/o/r.git");
+        assert_eq!(
+            remote("gitlab:group/sub/repo").unwrap(),
+            "https://gitlab.com/group/sub/repo.git"
+        );
+        assert_eq!(
+            remote("gitea:git.example.com/o/r").unwrap(),
+            "https://git.example.com/o/r.git"
+        );
+        // Self-hosted instances push to the same host their API is on
+        assert_eq!(
+            remote("github:ghe.example.com/o/r").unwrap(),
+            "https://ghe.example.com/o/r.git"
+        );
+        assert_eq!(
+            remote("gitlab:gitlab.example.com:8443/group/repo").unwrap(),
+            "https://gitlab.example.com:8443/group/repo.git"
+        );
+        assert_eq!(
+            runeweave::git::GitHubClient::new("https://github.com", "o", "r").api_url,
+            "https://api.github.com"
+        );
+        assert_eq!(
+            runeweave::git::GitHubClient::new("https://ghe.example.com", "o", "r").api_url,
+            "https://ghe.example.com/api/v3"
+        );
+        assert_eq!(
+            runeweave::git::GitLabClient::new("https://gitlab.example.com:8443", "group/repo")
+                .api_url,
+            "https://gitlab.example.com:8443/api/v4"
+        );
+        for url in [
+            "file:///srv/git/demo.git",
+            "ssh://git@example.com/demo.git",
+            "https://example.com/demo.git",
+        ] {
+            let ops = runeweave::git::GitOps::new(url).unwrap();
+            assert_eq!(ops.remote_url, url);
+            assert!(ops.forge.is_none());
+        }
+
+        for spec in [
+            "github:o",
+            "github:o/r/x",
+            "gitlab:repo",
+            "gitlab:group//repo",
+            "gitlab:gitlab.example.com/repo",
+            "gitea:o/r",
+            "svn://example.com/demo",
+        ] {
+            let err = remote(spec).unwrap_err();
+            assert_eq!(err.exit_code(), 1, "{spec}");
+        }
+    }
+
+    #[test]
+    fn test_gitlab_opens_merge_request() {
+        let (api_url, server) = mock_forge(
+            201,
+            r#"{"web_url": "https://gitlab.test/group/sub/repo/-/merge_requests/1"}"#,
+        );
+        let client = runeweave::git::GitLabClient {
+            api_url,
+            project: "group/sub/repo".to_string(),
+            token: Some("secret".to_string()),
+        };
+        let pr = runeweave::git::PullRequest {
+            title: "Scaffold demo".to_string(),
+            body: "seed: 1".to_string(),
+            head: "runeweave/demo-1".to_string(),
+            base: "main".to_string(),
+        };
+        use runeweave::git::ForgeClient;
+        let url = client.open_pull_request(&pr).unwrap();
+        assert_eq!(url, "https://gitlab.test/group/sub/repo/-/merge_requests/1");
+
+        let (method, path, auth, body) = server.join().unwrap();
+        assert_eq!(method, "POST");
+        assert_eq!(path, "/projects/group%2Fsub%2Frepo/merge_requests");
+        assert_eq!(auth, "Bearer secret");
+        let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(body["source_branch"], "runeweave/demo-1");
+        assert_eq!(body["target_branch"], "main");
+        assert_eq!(body["description"], "seed: 1");
+    }
+
+    #[test]
+    fn test_gitea_opens_pull_request() {
+        let (web_url, server) =
+            mock_forge(201, r#"{"html_url": "https://gitea.test/o/r/pulls/1"}"#);
+        let mut client = runeweave::git::GiteaClient::new(&web_url, "o", "r");
+        client.token = Some("secret".to_string());
+        let pr = runeweave::git::PullRequest {
+            title: "Scaffold demo".to_string(),
+            body: "seed: 1".to_string(),
+            head: "runeweave/demo-1".to_string(),
+            base: "main".to_string(),
+        };
+        use runeweave::git::ForgeClient;
+        let url = client.open_pull_request(&pr).unwrap();
+        assert_eq!(url, "https://gitea.test/o/r/pulls/1");
+
+        let (method, path, auth, body) = server.join().unwrap();
+        assert_eq!(method, "POST");
+        assert_eq!(path, "/api/v1/repos/o/r/pulls");
+        assert_eq!(auth, "token secret");
+        let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(body["head"], "runeweave/demo-1");
+        assert_eq!(body["base"], "main");
+        assert_eq!(body["title"], "Scaffold demo");
+    }
+
+    #[test]
+    fn test_ssh_credentials_name_a_user_first() {
+        use git2::CredentialType;
+        let config = git2::Config::new().unwrap();
+        let url = "ssh://example.com/demo.git";
+        let credentials = |username, allowed| {
+            runeweave::git::credentials(&config, None, url, username, allowed, 1)
+                .unwrap()
+                .credtype()
+        };
+        // ssh://host/repo names no user, so libgit2 asks for one before a key
+        assert_eq!(
+            credentials(None, CredentialType::USERNAME),
+            CredentialType::USERNAME.bits()
+        );
+        assert_eq!(
+            credentials(Some("deploy"), CredentialType::USERNAME),
+            CredentialType::USERNAME.bits()
+        );
+        assert_eq!(
+            credentials(None, CredentialType::USERNAME | CredentialType::SSH_KEY),
+            CredentialType::USERNAME.bits()
+        );
+    }
+
+    #[test]
+    fn test_push_branches_off_existing_repository() {
+        let dir = TempDir::new().unwrap();
+        let remote = dir.path().join("remote.git");
+        let remote_repo = git2::Repository::init_bare(&remote).unwrap();
+
+        // A clone with history, scaffolded in place
+        let clone = dir.path().join("clone");
+        let repo = git2::Repository::init(&clone).unwrap();
+        fs::write(clone.join("notes.md"), "keep me\n").unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(std::path::Path::new("notes.md")).unwrap();
+        index.write().unwrap();
+        let mut config = repo.config().unwrap();
+        config.set_str("user.name", "Dev").unwrap();
+        config.set_str("user.email", "dev@example.com").unwrap();
+        let sig = git2::Signature::now("Dev", "dev@example.com").unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let initial = repo
+            .commit(Some("HEAD"), &sig, &sig, "Initial commit", &tree, &[])
+            .unwrap();
+
+        let out = clone.clone();
+        fs::create_dir_all(out.join("services/api")).unwrap();
+        fs::write(out.join("services/api/Cargo.toml"), "[package]\n").unwrap();
+        fs::write(out.join("stray.txt"), "not generated\n").unwrap();
+
+        // Untracked files make the worktree dirty
+        let err = runeweave::git::ensure_clean(&out).unwrap_err();
+        assert_eq!(err.exit_code(), 3);
+        assert!(err.to_string().contains("--force"), "{err}");
+
+        let git_ops = runeweave::git::GitOps {
+            remote_url: remote.to_string_lossy().into_owned(),
+            forge: None,
+        };
+        let pr = runeweave::git::PullRequest {
+            title: "Scaffold demo".to_string(),
+            body: String::new(),
+            head: "runeweave/demo-1".to_string(),
+            base: "main".to_string(),
+        };
+        let files = [std::path::PathBuf::from("services/api/Cargo.toml")];
+        let message = "Scaffold demo\n\nPlan-Hash: abc\nSeed: 1\n";
+        let scaffold_commit = runeweave::git::ScaffoldCommit {
+            message: message.to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            git_ops
+                .push_to_repo(&out, &files, &scaffold_commit, &pr)
+                .unwrap(),
+            None
+        );
+
+        // The branch builds on HEAD, is checked out and holds only the scaffold
+        let head = repo.head().unwrap();
+        assert_eq!(head.shorthand(), Some("runeweave/demo-1"));
+        let commit = head.peel_to_commit().unwrap();
+        assert_eq!(commit.parent_id(0).unwrap(), initial);
+        assert_eq!(commit.message(), Some(message));
+        // The author comes from git config
+        assert_eq!(commit.author().email(), Some("dev@example.com"));
+        let tree = commit.tree().unwrap();
+        assert!(tree.get_path("notes.md".as_ref()).is_ok());
+        assert!(tree.get_path("services/api/Cargo.toml".as_ref()).is_ok());
+        assert!(tree.get_path("stray.txt".as_ref()).is_err());
+        assert!(repo.find_remote("origin").is_err());
+
+        let pushed = remote_repo
+            .find_reference("refs/heads/runeweave/demo-1")
+            .unwrap()
+            .target();
+        assert_eq!(pushed, Some(commit.id()));
+
+        // Only the stray file is left uncommitted
+        let statuses = repo.statuses(None).unwrap();
+        let changed: Vec<_> = statuses
+            .iter()
+            .filter_map(|s| s.path().map(str::to_string))
+            .collect();
+        assert_eq!(changed, ["stray.txt"]);
+
+        // An existing branch is never overwritten
+        let err = git_ops
+            .push_to_repo(&out, &files, &scaffold_commit, &pr)
+            .unwrap_err();
+        assert_eq!(err.exit_code(), 3);
+
+        // A subdirectory of the clone is not the clone: the parent's dirty
+        // worktree is ignored and it gets neither a commit nor a branch
+        let nested = clone.join("nested");
+        fs::create_dir_all(nested.join("services/api")).unwrap();
+        fs::write(nested.join("services/api/Cargo.toml"), "[package]\n").unwrap();
+        runeweave::git::ensure_clean(&nested).unwrap();
+        let parent_head = repo.head().unwrap().target();
+        let pr = runeweave::git::PullRequest {
+            head: "runeweave/demo-2".to_string(),
+            base: "runeweave/demo-1".to_string(),
+            ..pr
+        };
+        git_ops
+            .push_to_repo(&nested, &files, &scaffold_commit, &pr)
+            .unwrap();
+        assert_eq!(repo.head().unwrap().target(), parent_head);
+        assert!(repo
+            .find_branch("runeweave/demo-2", git2::BranchType::Local)
+            .is_err());
+        let nested_repo = git2::Repository::open(&nested).unwrap();
+        assert_eq!(
+            nested_repo.head().unwrap().shorthand(),
+            Some("runeweave/demo-2")
+        );
+    }
+
+    #[test]
+    fn test_commit_message_template() {
+        let manifest = runeweave::manifest::generate_manifest(
+            "{}",
+            7,
+            "1.82",
+            "abc",
+            Default::default(),
+            Default::default(),
+        )
+        .unwrap();
+
+        let message =
+            runeweave::git::commit_message(None, "demo", "runeweave/demo-7", &manifest).unwrap();
+        assert_eq!(
+            message,
+            format!(
+                "Scaffold demo with RuneWeave\n\nPlan-Hash: {}\nSeed: 7\nTemplate-Hash: abc\n",
+                manifest.plan_hash
+            )
+        );
+
+        let template = "chore({{ project }}): scaffold on {{ branch }} (seed {{ seed }}, rust {{ toolchain }})";
+        let message =
+            runeweave::git::commit_message(Some(template), "demo", "scaffold", &manifest).unwrap();
+        assert_eq!(
+            message,
+            "chore(demo): scaffold on scaffold (seed 7, rust 1.82)"
+        );
+
+        assert!(
+            runeweave::git::commit_message(Some("{{ nope }}"), "demo", "b", &manifest).is_err()
+        );
+    }
+
+    #[test]
+    fn test_ssh_signed_scaffold_commit() {
+        if Command::new("ssh-keygen").arg("-?").output().is_err() {
+            println!("Skipping SSH signing test: ssh-keygen not found");
+            return;
         }
+
+        let dir = TempDir::new().unwrap();
+        let key = dir.path().join("id_ed25519");
+        let status = Command::new("ssh-keygen")
+            .args([
+                "-q",
+                "-t",
+                "ed25519",
+                "-N",
+                "",
+                "-C",
+                "bot@example.com",
+                "-f",
+            ])
+            .arg(&key)
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        let remote = dir.path().join("remote.git");
+        let remote_repo = git2::Repository::init_bare(&remote).unwrap();
+        let out = dir.path().join("out");
+        fs::create_dir_all(&out).unwrap();
+        fs::write(out.join("README.md"), "demo\n").unwrap();
+
+        let git_ops = runeweave::git::GitOps {
+            remote_url: remote.to_string_lossy().into_owned(),
+            forge: None,
+        };
+        let commit = runeweave::git::ScaffoldCommit {
+            message: "Scaffold demo".to_string(),
+            author: Some(runeweave::git::GitIdentity {
+                name: "Scaffold Bot".to_string(),
+                email: "bot@example.com".to_string(),
+            }),
+            committer: None,
+            sign: Some(runeweave::git::SigningFormat::Ssh),
+            signing_key: Some(key.to_string_lossy().into_owned()),
+        };
+        let pr = runeweave::git::PullRequest {
+            title: "Scaffold demo".to_string(),
+            body: String::new(),
+            head: "scaffold".to_string(),
+            base: "main".to_string(),
+        };
+        git_ops
+            .push_to_repo(&out, &["README.md".into()], &commit, &pr)
+            .unwrap();
+
+        // The remote was empty, so the scaffold became its base branch
+        let id = remote_repo
+            .find_reference("refs/heads/main")
+            .unwrap()
+            .target()
+            .unwrap();
+        let pushed = remote_repo.find_commit(id).unwrap();
+        assert_eq!(pushed.author().name(), Some("Scaffold Bot"));
+        assert_eq!(pushed.committer().name(), Some("Scaffold Bot"));
+
+        // The signature verifies against the signed commit content
+        let (signature, content) = remote_repo.extract_signature(&id, None).unwrap();
+        let signature = signature.as_str().unwrap();
+        assert!(signature.starts_with("-----BEGIN SSH SIGNATURE-----"));
+        let signature_file = dir.path().join("commit.sig");
+        fs::write(&signature_file, signature).unwrap();
+        let mut check = Command::new("ssh-keygen")
+            .args(["-Y", "check-novalidate", "-n", "git", "-s"])
+            .arg(&signature_file)
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::null())
+            .spawn()
+            .unwrap();
+        check.stdin.take().unwrap().write_all(&content).unwrap();
+        assert!(check.wait().unwrap().success());
+    }
+
+    #[test]
+    fn test_merge3() {
+        use runeweave::merge::merge3;
+        let labels = ("yours", "runeweave");
+        let base = "name: ci\non: push\nservices:\n  - api\nsteps: []\n";
+
+        // Changes to different lines combine
+        let ours = "name: custom\non: push\nservices:\n  - api\nsteps: []\n";
+        let theirs = "name: ci\non: push\nservices:\n  - api\n  - worker\nsteps: []\n";
+        let merged = merge3(base, ours, theirs, labels);
+        assert_eq!(merged.conflicts, 0);
+        assert_eq!(
+            merged.content,
+            "name: custom\non: push\nservices:\n  - api\n  - worker\nsteps: []\n"
+        );
+
+        // The same change on both sides is not a conflict
+        assert_eq!(merge3(base, theirs, theirs, labels).content, theirs);
+
+        // Overlapping changes become a conflict block
+        let ours = "name: ci\non: push\nservices:\n  - api # main\nsteps: []\n";
+        let merged = merge3(base, ours, theirs, labels);
+        assert_eq!(merged.conflicts, 1);
+        assert_eq!(
+            merged.content,
+            "name: ci\non: push\nservices:\n\
+             <<<<<<< yours\n  - api # main\n=======\n  - api\n  - worker\n>>>>>>> runeweave\n\
+             steps: []\n"
+        );
+
+        // Without a common base, differing files conflict as a whole
+        let merged = merge3("", "a\n", "b", labels);
+        assert_eq!(merged.conflicts, 1);
+        assert_eq!(
+            merged.content,
+            "<<<<<<< yours\na\n=======\nb\n>>>>>>> runeweave\n"
+        );
     }
 }